@@ -2,17 +2,72 @@ SET threads = 4;
 SET preserve_insertion_order = false;
 SET temp_directory = 'data/DVF/';
 
+DROP VIEW IF EXISTS Mutations_Flat;
+DROP TABLE IF EXISTS Prix_M2;
+DROP TABLE IF EXISTS Adresses;
 DROP TABLE IF EXISTS Classes;
+DROP TABLE IF EXISTS Parcelles;
+DROP TABLE IF EXISTS Dispositions;
 DROP TABLE IF EXISTS Mutations;
 
+-- The ids (idm, idd, idg) are hashes of the business keys (idmutation, iddispo, idpar),
+-- the same rows keep the same ids across runs.
 CREATE TABLE Mutations (
-	idg INTEGER PRIMARY KEY,
-	idpar VARCHAR NOT NULL,
-	idmutation INTEGER NOT NULL,
+	idm BIGINT PRIMARY KEY,
+	idmutation BIGINT NOT NULL,
 	vefa BOOLEAN NOT NULL,
 	typologie VARCHAR,
+	code_typologie UTINYINT NOT NULL,
 	datemut DATE NOT NULL,
 	nature VARCHAR,
+	code_nature UTINYINT NOT NULL,
+	codtypbien VARCHAR,
+	libtypbien VARCHAR,
+	sbati DECIMAL(12,2),
+	sterr DECIMAL(12,2),
+	nblocmut INTEGER,
+	nbpar INTEGER,
+	coddep VARCHAR,
+	l_codinsee VARCHAR[],
+	longitude DOUBLE,
+	latitude DOUBLE,
+	geometry BLOB,
+	source_code_insee VARCHAR,
+	source_nom_commune VARCHAR,
+	source_code_dep VARCHAR
+);
+
+CREATE TABLE Dispositions (
+	idd BIGINT PRIMARY KEY,
+	idm BIGINT NOT NULL,
+	iddispo BIGINT,
+	nodispo BIGINT,
+	valeur_fonciere DECIMAL(15,2) NOT NULL,
+	FOREIGN KEY (idm) REFERENCES Mutations(idm)
+);
+
+CREATE TABLE Parcelles (
+	idg BIGINT PRIMARY KEY,
+	idd BIGINT NOT NULL,
+	idpar VARCHAR NOT NULL,
+	vendu BOOLEAN,
+	nosect VARCHAR,
+	noplan VARCHAR,
+	FOREIGN KEY (idd) REFERENCES Dispositions(idd)
+);
+
+CREATE TABLE Classes (
+	idg BIGINT NOT NULL,
+	rank INTEGER NOT NULL,
+	libelle VARCHAR NOT NULL,
+	surface DECIMAL(12,2) NOT NULL,
+	PRIMARY KEY (idg, rank),
+	FOREIGN KEY (idg) REFERENCES Parcelles(idg)
+);
+
+CREATE TABLE Adresses (
+	idg BIGINT NOT NULL,
+	rank INTEGER NOT NULL,
 	btq VARCHAR,
 	voie VARCHAR,
 	novoie VARCHAR,
@@ -20,13 +75,62 @@ CREATE TABLE Mutations (
 	commune VARCHAR,
 	typvoie VARCHAR,
 	codepostal VARCHAR,
-	valeur_fonciere DECIMAL(15,2) NOT NULL,
-	vendu BOOLEAN
+	adresse_complete VARCHAR,
+	normalisee BOOLEAN NOT NULL,
+	PRIMARY KEY (idg, rank),
+	FOREIGN KEY (idg) REFERENCES Parcelles(idg)
 );
 
-CREATE TABLE Classes (
-	idg INTEGER NOT NULL,
-	libelle VARCHAR NOT NULL,
-	surface DECIMAL(12,2) NOT NULL,
-	FOREIGN KEY (idg) REFERENCES Mutations(idg)
+-- Price per square metre of a mutation, 'regle' is the rule applied to compute it :
+-- built_surface, single_class, total_built_surface, or land_only & no_surface without price.
+CREATE TABLE Prix_M2 (
+	idm BIGINT PRIMARY KEY,
+	valeur_fonciere DECIMAL(15,2) NOT NULL,
+	surface DECIMAL(12,2),
+	prix_m2 DECIMAL(15,2),
+	regle VARCHAR NOT NULL,
+	FOREIGN KEY (idm) REFERENCES Mutations(idm)
 );
+
+-- Compatibility view : one row per parcel, as the former flat table 'Mutations'.
+-- The 'valeur_fonciere' is repeated on every parcel of a disposition, don't sum it.
+CREATE VIEW Mutations_Flat AS
+SELECT
+	p.idg,
+	p.idpar,
+	m.idmutation,
+	m.vefa,
+	m.typologie,
+	m.datemut,
+	m.nature,
+	a.btq,
+	a.voie,
+	a.novoie,
+	a.codvoie,
+	a.commune,
+	a.typvoie,
+	a.codepostal,
+	d.valeur_fonciere,
+	p.vendu,
+	m.source_code_insee,
+	m.source_nom_commune,
+	m.source_code_dep,
+	d.iddispo,
+	d.nodispo,
+	p.nosect,
+	p.noplan,
+	m.codtypbien,
+	m.libtypbien,
+	m.sbati,
+	m.sterr,
+	m.nblocmut,
+	m.nbpar,
+	m.coddep,
+	m.l_codinsee,
+	m.longitude,
+	m.latitude,
+	m.geometry
+FROM Parcelles p
+JOIN Dispositions d ON d.idd = p.idd
+JOIN Mutations m ON m.idm = d.idm
+LEFT JOIN Adresses a ON a.idg = p.idg AND a.rank = 0;
@@ -1,22 +1,44 @@
 CREATE TEMPORARY TABLE mutations_to_delete AS
 WITH mutations_duplicate AS (
     SELECT
-        idg,
-        row_number() OVER (PARTITION BY idg, idpar, datemut ORDER BY idg) as rn
+        idm,
+        row_number() OVER (PARTITION BY idmutation ORDER BY idm) as rn
     FROM
         Mutations
 )
 SELECT
-    idg
+    idm
 FROM
     mutations_duplicate
 WHERE
     rn > 1;
 
-DELETE FROM Mutations 
-WHERE idg IN (SELECT idg FROM mutations_to_delete);
+CREATE TEMPORARY TABLE parcelles_to_delete AS
+SELECT
+    p.idg
+FROM
+    Parcelles p
+    JOIN Dispositions d ON d.idd = p.idd
+WHERE
+    d.idm IN (SELECT idm FROM mutations_to_delete);
+
+DELETE FROM Adresses
+WHERE idg IN (SELECT idg FROM parcelles_to_delete);
 
 DELETE FROM Classes
-WHERE idg IN (SELECT idg from mutations_to_delete);
+WHERE idg IN (SELECT idg FROM parcelles_to_delete);
+
+DELETE FROM Parcelles
+WHERE idg IN (SELECT idg FROM parcelles_to_delete);
+
+DELETE FROM Prix_M2
+WHERE idm IN (SELECT idm FROM mutations_to_delete);
+
+DELETE FROM Dispositions
+WHERE idm IN (SELECT idm FROM mutations_to_delete);
+
+DELETE FROM Mutations
+WHERE idm IN (SELECT idm FROM mutations_to_delete);
 
-DROP TABLE mutations_to_delete;
\ No newline at end of file
+DROP TABLE parcelles_to_delete;
+DROP TABLE mutations_to_delete;
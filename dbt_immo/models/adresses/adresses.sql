@@ -0,0 +1 @@
+SELECT * FROM DVF."adresses.parquet" 
\ No newline at end of file
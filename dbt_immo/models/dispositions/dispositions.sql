@@ -0,0 +1 @@
+SELECT * FROM DVF."dispositions.parquet" 
\ No newline at end of file
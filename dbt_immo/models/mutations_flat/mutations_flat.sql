@@ -0,0 +1,39 @@
+SELECT
+    p.idg,
+    p.idpar,
+    m.idmutation,
+    m.vefa,
+    m.typologie,
+    m.datemut,
+    m.nature,
+    a.btq,
+    a.voie,
+    a.novoie,
+    a.codvoie,
+    a.commune,
+    a.typvoie,
+    a.codepostal,
+    d.valeur_fonciere,
+    p.vendu,
+    m.source_code_insee,
+    m.source_nom_commune,
+    m.source_code_dep,
+    d.iddispo,
+    d.nodispo,
+    p.nosect,
+    p.noplan,
+    m.codtypbien,
+    m.libtypbien,
+    m.sbati,
+    m.sterr,
+    m.nblocmut,
+    m.nbpar,
+    m.coddep,
+    m.l_codinsee,
+    m.longitude,
+    m.latitude,
+    m.geometry
+FROM {{ ref('parcelles') }} p
+JOIN {{ ref('dispositions') }} d ON d.idd = p.idd
+JOIN {{ ref('mutations') }} m ON m.idm = d.idm
+LEFT JOIN {{ ref('adresses') }} a ON a.idg = p.idg AND a.rank = 0
@@ -0,0 +1 @@
+SELECT * FROM DVF."parcelles.parquet" 
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT * FROM DVF."prix_m2.parquet" 
\ No newline at end of file
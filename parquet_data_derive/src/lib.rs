@@ -0,0 +1,253 @@
+//! `#[derive(ParquetData)]` : generate the ***to_arrays***, ***get_schema*** & ***from_arrays*** of the trait
+//! `crate::transform::parquet_data::ParquetData` from the fields of the struct.
+//!
+//! Each field is a column, its type must implement `ParquetColumn` (an ***Option*** is a nullable column).
+//! The attributes `#[parquet(...)]` of a field :
+//! - `rename = "name"` : name of the column, the name of the field by default
+//! - `precision = EXPR` : precision of the decimals, ***SURFACE_PRECISION*** by default
+//! - `flatten` / `flatten(prefix = "source_")` : the columns of the struct of the field, it must derive ***ParquetData***
+//! - `derived(name = "code", with = PATH)` : another column after the field, computed by the function ***PATH*** from the field
+//!
+//! The attribute `#[parquet(writer = EXPR)]` of the struct sets the ***WRITER_CONFIG*** of the table.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Expr, Fields, LitStr, parse_macro_input};
+
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    precision: Option<Expr>,
+    flatten: Option<String>,
+    derived: Vec<(String, Expr)>,
+}
+
+fn parse_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("parquet"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("nullable") {
+                // A forced nullable column on a required field would be rejected by `from_arrays`
+                return Err(meta.error(
+                    "`nullable` isn't supported, use an `Option` field for a nullable column",
+                ));
+            } else if meta.path.is_ident("precision") {
+                options.precision = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("flatten") {
+                let mut prefix = String::new();
+                if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|nested| {
+                        if nested.path.is_ident("prefix") {
+                            prefix = nested.value()?.parse::<LitStr>()?.value();
+                            Ok(())
+                        } else {
+                            Err(nested.error("expected `prefix`"))
+                        }
+                    })?;
+                }
+                options.flatten = Some(prefix);
+            } else if meta.path.is_ident("derived") {
+                let mut name = None;
+                let mut with = None;
+                meta.parse_nested_meta(|nested| {
+                    if nested.path.is_ident("name") {
+                        name = Some(nested.value()?.parse::<LitStr>()?.value());
+                    } else if nested.path.is_ident("with") {
+                        with = Some(nested.value()?.parse::<Expr>()?);
+                    } else {
+                        return Err(nested.error("expected `name` or `with`"));
+                    }
+                    Ok(())
+                })?;
+
+                match (name, with) {
+                    (Some(name), Some(with)) => options.derived.push((name, with)),
+                    _ => return Err(meta.error("`derived` expects a `name` and a `with`")),
+                }
+            } else {
+                return Err(meta.error("unsupported parquet attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+fn parse_writer(input: &DeriveInput) -> syn::Result<Option<Expr>> {
+    let mut writer = None;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("parquet"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("writer") {
+                writer = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported parquet attribute, expected `writer`"))
+            }
+        })?;
+    }
+
+    Ok(writer)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let writer = parse_writer(input)?.map(|writer| {
+        quote! {
+            const WRITER_CONFIG: crate::transform::parquet_sink::WriterConfig = #writer;
+        }
+    });
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "ParquetData can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "ParquetData can only be derived for structs",
+            ));
+        }
+    };
+
+    let module = quote!(crate::transform::parquet_data);
+    let mut schema = Vec::new();
+    let mut arrays = Vec::new();
+    let mut reads = Vec::new();
+    let mut inits = Vec::new();
+
+    for field in fields {
+        let options = parse_options(field)?;
+        let ident = field.ident.as_ref().expect("Named field without ident");
+        let ty = &field.ty;
+        let values = format_ident!("column_{}", ident);
+        let precision = match &options.precision {
+            Some(precision) => quote!(#precision),
+            None => quote!(#module::SURFACE_PRECISION),
+        };
+
+        if let Some(prefix) = &options.flatten {
+            schema.push(quote! {
+                for field in <#ty as #module::ParquetData>::get_schema().fields() {
+                    fields.push(::arrow::datatypes::Field::new(
+                        format!("{}{}", #prefix, field.name()),
+                        field.data_type().clone(),
+                        field.is_nullable(),
+                    ));
+                }
+            });
+            arrays.push(quote! {
+                let nested = data
+                    .iter()
+                    .map(|row| row.#ident.clone())
+                    .collect::<Vec<#ty>>();
+                arrays.extend(<#ty as #module::ParquetData>::to_arrays(&nested));
+            });
+            reads.push(quote! {
+                let size = <#ty as #module::ParquetData>::get_schema().fields().len();
+                let nested = arrays
+                    .get(index..index + size)
+                    .ok_or_else(|| format!("Missing the columns of '{}'", stringify!(#ident)))?;
+                let mut #values = <#ty as #module::ParquetData>::from_arrays(nested)?.into_iter();
+                index += size;
+            });
+            inits.push(quote! {
+                #ident: #values
+                    .next()
+                    .ok_or_else(|| format!("Missing rows in the columns of '{}'", stringify!(#ident)))?
+            });
+        } else {
+            let column = options.rename.unwrap_or_else(|| ident.to_string());
+
+            schema.push(quote! {
+                fields.push(#module::field_of::<#ty>(#column, #precision));
+            });
+            arrays.push(quote! {
+                let values = data.iter().map(|row| Some(&row.#ident)).collect::<Vec<_>>();
+                arrays.push(<#ty as #module::ParquetColumn>::to_array(&values, #precision));
+            });
+            reads.push(quote! {
+                let mut #values =
+                    <#ty as #module::ParquetColumn>::from_array(#module::column(arrays, index)?)?
+                        .into_iter();
+                index += 1;
+            });
+            inits.push(quote! {
+                #ident: #values
+                    .next()
+                    .flatten()
+                    .ok_or_else(|| format!("Null value in the column '{}'", #column))?
+            });
+        }
+
+        for (column, with) in &options.derived {
+            schema.push(quote! {
+                fields.push(#module::derived_field::<#ty, _, _>(#column, #with, #precision));
+            });
+            arrays.push(quote! {
+                let values = data.iter().map(|row| (#with)(&row.#ident)).collect::<Vec<_>>();
+                arrays.push(#module::column_of(&values, #precision));
+            });
+            reads.push(quote! {
+                index += 1;
+            });
+        }
+    }
+
+    Ok(quote! {
+        impl #module::ParquetData for #name {
+            #writer
+
+            fn to_arrays(data: &[Self]) -> Vec<::arrow::array::ArrayRef> {
+                let mut arrays: Vec<::arrow::array::ArrayRef> = Vec::new();
+                #({ #arrays })*
+                arrays
+            }
+
+            fn get_schema() -> ::arrow::datatypes::Schema {
+                let mut fields: Vec<::arrow::datatypes::Field> = Vec::new();
+                #({ #schema })*
+                ::arrow::datatypes::Schema::new(fields)
+            }
+
+            #[allow(unused_assignments)]
+            fn from_arrays(arrays: &[::arrow::array::ArrayRef]) -> Result<Vec<Self>, String> {
+                let mut index = 0usize;
+                #(#reads)*
+
+                let rows = arrays.first().map(|array| array.len()).unwrap_or_default();
+                (0..rows)
+                    .map(|_| Ok(Self { #(#inits),* }))
+                    .collect()
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(ParquetData, attributes(parquet))]
+pub fn derive_parquet_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -1,6 +1,6 @@
 use std::fs::{self, DirEntry, OpenOptions};
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use futures::future::join_all;
@@ -12,11 +12,17 @@ use serde_json::{Map, Value};
 use tokio::sync::Semaphore;
 use tokio::time::{Duration, sleep};
 
-use super::geometry::split_geometry;
-use super::utils::IdGenerator;
-use crate::transform::api_dvf::transform_api_data;
+use super::geometry::{Geometry, simplify_geometry, split_geometry};
+use super::report::{COVERAGE_PATH, FeatureReport, REPORT_PATH, RunReport, TileReport, TileStatus};
+use super::validation::{is_valid, validate_geometry};
+use crate::transform::api_dvf::{
+    CLASS_FILTER, TransformMode, remove_duplicates_mutations, remove_duplicates_rejected,
+    transform_api_data,
+};
 use crate::transform::parquet_data::ParquetData;
-use crate::transform::tables::{Classes, Mutation};
+use crate::transform::partition::{OutputLayout, partition_tables, remove_feature_files};
+use crate::transform::quarantine::{QUARANTINE_FOLDER, QuarantineRecord, write_to_jsonl};
+use crate::transform::tables::{FeatureSource, RejectedClass, Tables};
 
 const FILTERS: [(&str, &str); 3] = [
     ("valeurfonc[lte]", "100000000000000000"),
@@ -26,6 +32,82 @@ const FILTERS: [(&str, &str); 3] = [
 
 pub const TARGET_FOLDER: &str = "data/DVF/extracted";
 
+/// Tolerance (in degrees) used to simplify the geometries before querying the API.<br>
+/// Set to ***None*** to send the geometries as they are in the GeoJSON files.
+const SIMPLIFY_TOLERANCE: Option<f64> = Some(0.0005);
+
+/// With [TransformMode::Lenient] the malformed records are written in the ***QUARANTINE_FOLDER***
+/// instead of rejecting the whole API response.
+const TRANSFORM_MODE: TransformMode = TransformMode::Lenient;
+
+/// Argument of the command line to run the transformation in [TransformMode::Strict].
+const STRICT_ARG: &str = "--strict";
+
+/// Rows extracted from the API responses of a feature, moved to the CPU workers and back.
+#[derive(Debug, Default)]
+pub(crate) struct FeatureRows {
+    pub(crate) tables: Tables,
+    pub(crate) quarantine: Vec<QuarantineRecord>,
+    pub(crate) rejected_classes: Vec<RejectedClass>,
+}
+
+impl FeatureRows {
+    /// Move the rows of a tile at the end of the rows of the feature.
+    fn append(&mut self, other: &mut FeatureRows) {
+        self.tables.append(&mut other.tables);
+        self.quarantine.append(&mut other.quarantine);
+        self.rejected_classes.append(&mut other.rejected_classes);
+    }
+}
+
+/// What the tasks of the features share to query the API.
+#[derive(Debug, Clone)]
+struct ApiContext {
+    api_key: String,
+    headers: HeaderMap,
+    regex_error: Regex,
+    cpu_pool: Arc<Semaphore>,
+}
+
+/// Body of the POST request on the endpoint ***mutation/search***.
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchRequest<'a> {
+    pub(crate) geojson: &'a Geometry,
+}
+
+/// Return the pool bounding the CPU bound tasks (parsing & Parquet writing), one worker per core.
+pub fn new_cpu_pool() -> Arc<Semaphore> {
+    let workers = std::thread::available_parallelism()
+        .map(|workers| workers.get())
+        .unwrap_or(4);
+
+    Arc::new(Semaphore::new(workers))
+}
+
+/// Run the CPU bound ***function*** on the blocking threads, once a worker of the ***cpu_pool*** is free.<br>
+/// The fetchers wait for a free worker before sending their next request,
+/// so the runtime threads stay available for the network and the responses don't pile up.
+pub async fn run_blocking<T, F>(cpu_pool: &Semaphore, function: F) -> Result<T, ()>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let _permit = cpu_pool.acquire().await.map_err(|e| error!("{}", e))?;
+
+    tokio::task::spawn_blocking(function)
+        .await
+        .map_err(|e| error!("Failed to run the blocking task : {}", e))
+}
+
+/// Return the [TransformMode] selected by the arguments of the command line, ***TRANSFORM_MODE*** by default.
+fn transform_mode() -> TransformMode {
+    if std::env::args().any(|arg| arg == STRICT_ARG) {
+        TransformMode::Strict
+    } else {
+        TRANSFORM_MODE
+    }
+}
+
 /// Return the API Key stored in the ***.env*** at the root
 fn get_api_key() -> Result<String, String> {
     let mut buffer = String::new();
@@ -105,45 +187,135 @@ fn get_department(file_path: PathBuf) -> Result<Value, ()> {
     Ok(value)
 }
 
+/// Validate the ***geometry*** of the feature and simplify it with the ***SIMPLIFY_TOLERANCE***.<br>
+/// Return None when the geometry is rejected, the issues are reported in the ***feature_report***.
+fn prepare_geometry(geometry: &Value, feature_report: &mut FeatureReport) -> Option<Geometry> {
+    let feature_id = &feature_report.feature_id;
+
+    let geometry = match validate_geometry(geometry, &mut feature_report.geometry_issues) {
+        Ok(geometry) => geometry,
+        Err(message) => {
+            error!("{} - {}", feature_id, message);
+            feature_report.valid_geometry = false;
+
+            // The rejected geometry stays on the coverage map when it can still be parsed
+            if let Ok(geometry) = serde_json::from_value::<Geometry>(geometry.clone()) {
+                let mut tile = TileReport::new(geometry, 0);
+                tile.status = TileStatus::Failed;
+                feature_report.tiles.push(tile);
+            }
+            return None;
+        }
+    };
+
+    let geometry = match SIMPLIFY_TOLERANCE {
+        Some(tolerance) => match simplify_geometry(&geometry, tolerance) {
+            Ok(simplified) if is_valid(&simplified) => {
+                info!(
+                    "{} - Simplified the geometry from {} to {} points",
+                    feature_id,
+                    geometry.size(),
+                    simplified.size()
+                );
+                simplified
+            }
+            Ok(_) => {
+                warn!("{} - The simplified geometry is invalid", feature_id);
+                geometry
+            }
+            Err(message) => {
+                warn!(
+                    "{} - Failed to simplify the geometry : {}",
+                    feature_id, message
+                );
+                geometry
+            }
+        },
+        None => geometry,
+    };
+    Some(geometry)
+}
+
 async fn process_feature(
     feature_id: &str,
-    id_generator: &IdGenerator,
-    api_key: &str,
-    headers: &HeaderMap,
-    data: Map<String, Value>,
-    regex_error: &Regex,
+    feature_report: &mut FeatureReport,
+    context: &ApiContext,
+    geometry: Geometry,
+    source: FeatureSource,
 ) -> Result<(), ()> {
     let mut failed_retry = false;
-    let mut buffer: Vec<Map<String, Value>> = Vec::new();
-    buffer.push(data);
+    let mut buffer: Vec<TileReport> = Vec::new();
+    buffer.push(TileReport::new(geometry, 0));
+
+    let mut rows = FeatureRows::default();
+    let transform_mode = transform_mode();
 
-    let mut mutations: Vec<Mutation> = Vec::new();
-    let mut classes: Vec<Classes> = Vec::new();
+    while let Some(tile) = buffer.last_mut() {
+        tile.attempts += 1;
 
-    while !buffer.is_empty() {
         let api_response = api_post(
             "mutation/search",
-            api_key,
-            headers.clone(),
-            &buffer.last(),
+            &context.api_key,
+            context.headers.clone(),
+            &SearchRequest {
+                geojson: &tile.geometry,
+            },
             &FILTERS,
         )
         .await;
 
         match (api_response, failed_retry) {
             (Ok(response), _) => {
-                let content = response
-                    .text()
-                    .await
-                    .map_err(|e| error!("Failed to extract the response text : {}", e))?;
-
-                let _ = transform_api_data(content, id_generator, &mut mutations, &mut classes);
-
-                let _ = buffer.pop();
+                let content = match response.bytes().await {
+                    Ok(content) => content,
+                    Err(e) => {
+                        error!(
+                            "{} - Failed to extract the response body : {}",
+                            feature_id, e
+                        );
+                        tile.status = TileStatus::Failed;
+                        feature_report.tiles.extend(buffer.pop());
+                        continue;
+                    }
+                };
+
+                let source = source.clone();
+
+                // The tile is transformed in its own rows : if the worker panics only the tile fails,
+                // the rows of the previous tiles are kept
+                let tile_rows = run_blocking(&context.cpu_pool, move || {
+                    let mut tile_rows = FeatureRows::default();
+                    transform_api_data(
+                        &content,
+                        &source,
+                        transform_mode,
+                        &mut tile_rows.tables,
+                        &mut tile_rows.quarantine,
+                        &mut tile_rows.rejected_classes,
+                    )
+                    .map(|_| tile_rows)
+                })
+                .await;
+
+                tile.status = match tile_rows {
+                    Ok(Ok(mut tile_rows)) => {
+                        tile.rows = tile_rows.tables.mutations.len();
+                        rows.append(&mut tile_rows);
+
+                        if tile.rows > 0 {
+                            TileStatus::Queried
+                        } else {
+                            TileStatus::Empty
+                        }
+                    }
+                    Ok(Err(_)) | Err(_) => TileStatus::Failed,
+                };
+                feature_report.tiles.extend(buffer.pop());
             }
             (Err(message), true) => {
                 error!("{} - {}", feature_id, message);
-                let _ = buffer.pop();
+                tile.status = TileStatus::Failed;
+                feature_report.tiles.extend(buffer.pop());
                 failed_retry = false;
             }
             (Err(message), false) => {
@@ -151,69 +323,165 @@ async fn process_feature(
                 if message.contains("402") || message.contains("501") {
                     sleep(Duration::from_secs(60)).await;
                     failed_retry = true;
-                } else if regex_error.is_match(&message) {
-                    let geometry =
-                        &buffer
-                            .last()
-                            .unwrap()
-                            .get("geojson")
-                            .ok_or(())
-                            .map_err(|_| {
-                                error!(
-                                    "Inconsistant data format whith none 'geojson' key : {:?}",
-                                    buffer.last()
-                                )
-                            })?;
-
-                    match split_geometry(geometry) {
+                } else if context.regex_error.is_match(&message) {
+                    match split_geometry(&tile.geometry) {
                         Ok((geometry1, geometry2)) => {
-                            let mut data1 = Map::new();
-                            data1.insert("geojson".to_string(), geometry1);
-
-                            let mut data2 = Map::new();
-                            data2.insert("geojson".to_string(), geometry2);
-
-                            let _ = buffer.pop();
-                            buffer.push(data1);
-                            buffer.push(data2);
+                            let depth = tile.depth + 1;
+                            tile.status = TileStatus::Split;
+                            feature_report.tiles.extend(buffer.pop());
+                            buffer.push(TileReport::new(geometry1, depth));
+                            buffer.push(TileReport::new(geometry2, depth));
                         }
                         Err(message) => {
                             error!(message);
-                            let _ = buffer.pop();
+                            tile.status = TileStatus::Failed;
+                            feature_report.tiles.extend(buffer.pop());
                         }
                     }
                 } else {
-                    let _ = buffer.pop();
+                    tile.status = TileStatus::Failed;
+                    feature_report.tiles.extend(buffer.pop());
                 }
             }
         }
     }
 
-    let folder_path = PathBuf::from(TARGET_FOLDER);
-    let mutations_path = folder_path.join(format!("mutations_{}.parquet", feature_id));
-    let classes_path = folder_path.join(format!("classes_{}.parquet", feature_id));
+    feature_report.duplicates_removed = remove_duplicates_mutations(&mut rows.tables);
+    remove_duplicates_rejected(&mut rows.rejected_classes);
+    if feature_report.duplicates_removed > 0 {
+        info!(
+            "{} - Removed {} duplicated mutations",
+            feature_id, feature_report.duplicates_removed
+        );
+    }
 
-    if !mutations.is_empty() {
-        ParquetData::write_to_parquet(&mutations, &mutations_path)
-            .map_err(|e| error!("Failed to save mutations : {:?}", e))?;
+    for class in std::mem::take(&mut rows.rejected_classes) {
+        *feature_report
+            .classes_rejected
+            .entry(class.reason)
+            .or_default() += 1;
+
+        if CLASS_FILTER.quarantine {
+            rows.quarantine.push(QuarantineRecord::new(
+                class.path,
+                class.reason.as_str().to_string(),
+                class.record,
+            ));
+        }
+    }
 
-        ParquetData::write_to_parquet(&classes, &classes_path)
-            .map_err(|e| error!("Failed to save classes : {:?}", e))?;
+    feature_report.quarantined = rows.quarantine.len();
 
-        Ok(())
-    } else {
+    if rows.tables.is_empty() {
         warn!("Incomplete values {}", feature_id);
-        Err(())
     }
+
+    let feature_id = feature_id.to_string();
+    run_blocking(&context.cpu_pool, move || {
+        write_rows(
+            Path::new(TARGET_FOLDER),
+            Path::new(QUARANTINE_FOLDER),
+            &feature_id,
+            &rows,
+        )
+    })
+    .await?
+}
+
+/// Write the quarantine and the Parquet files of the tables of the feature ***feature_id***
+/// into the ***quarantine_folder*** and the ***target_folder***, in the layout of [OutputLayout::from_args].
+pub(crate) fn write_rows(
+    target_folder: &Path,
+    quarantine_folder: &Path,
+    feature_id: &str,
+    rows: &FeatureRows,
+) -> Result<(), ()> {
+    let quarantine_path = quarantine_folder.join(format!("quarantine_{}.jsonl", feature_id));
+
+    if rows.quarantine.is_empty() {
+        // The records of a previous run were fixed since
+        let _ = fs::remove_file(&quarantine_path);
+    } else if let Err(e) = write_to_jsonl(&rows.quarantine, &quarantine_path) {
+        // The valid rows of the feature are still written
+        error!("{} - Failed to save the quarantine : {}", feature_id, e);
+    }
+
+    // The files of a previous run are removed first, the feature may have no rows left
+    remove_feature_files(target_folder, feature_id).map_err(|e| {
+        error!(
+            "{} - Failed to remove the previous Parquet files : {}",
+            feature_id, e
+        )
+    })?;
+
+    let tables = &rows.tables;
+
+    if tables.is_empty() {
+        return Err(());
+    }
+
+    let layout = OutputLayout::from_args();
+    let file_name = |table: &str| layout.file_name(table, feature_id);
+
+    match layout {
+        OutputLayout::Flat => write_tables(tables, |table| target_folder.join(file_name(table))),
+        OutputLayout::Partitioned => {
+            for (key, tables) in partition_tables(tables) {
+                write_tables(&tables, |table| {
+                    key.folder(target_folder, table).join(file_name(table))
+                })?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write the Parquet file of each table at the ***file_path*** of the table, its folder is created if needed.
+fn write_tables(tables: &Tables, file_path: impl Fn(&str) -> PathBuf) -> Result<(), ()> {
+    let file_path = |table: &str| -> Result<PathBuf, ()> {
+        let path = file_path(table);
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)
+                .map_err(|e| error!("Failed to create the folder {:?} : {}", folder, e))?;
+        }
+        Ok(path)
+    };
+
+    let [
+        mutations,
+        dispositions,
+        parcelles,
+        classes,
+        adresses,
+        prix_m2,
+    ] = Tables::NAMES;
+
+    ParquetData::write_to_parquet(&tables.mutations, &file_path(mutations)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", mutations, e))?;
+
+    ParquetData::write_to_parquet(&tables.dispositions, &file_path(dispositions)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", dispositions, e))?;
+
+    ParquetData::write_to_parquet(&tables.parcelles, &file_path(parcelles)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", parcelles, e))?;
+
+    ParquetData::write_to_parquet(&tables.classes, &file_path(classes)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", classes, e))?;
+
+    ParquetData::write_to_parquet(&tables.adresses, &file_path(adresses)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", adresses, e))?;
+
+    ParquetData::write_to_parquet(&tables.prix_m2, &file_path(prix_m2)?)
+        .map_err(|e| error!("Failed to save {} : {:?}", prix_m2, e))?;
+
+    Ok(())
 }
 
 async fn process_features(
     features: Vec<Map<String, Value>>,
-    id_generator: &IdGenerator,
-    api_key: &str,
-    headers: &HeaderMap,
+    run_report: &RunReport,
+    context: &ApiContext,
     dpt: usize,
-    regex_error: &Regex,
 ) -> Result<(), ()> {
     let semaphore = Arc::new(Semaphore::new(100));
     let mut tasks = Vec::new();
@@ -225,36 +493,61 @@ async fn process_features(
             .await
             .map_err(|e| error!("{}", e))?;
 
-        let id_generator_clone = id_generator.clone();
-        let api_key_clone = api_key.to_owned();
-        let headers_clone = headers.clone();
-        let regex_error_clone = regex_error.clone();
+        let run_report_clone = run_report.clone();
+        let context_clone = context.clone();
 
-        let geometry = features
+        let feature = features
             .get(index)
             .ok_or(())
-            .map_err(|_| error!("Failed to get the feature {}", index))?
+            .map_err(|_| error!("Failed to get the feature {}", index))?;
+
+        let geometry = feature
             .get("geometry")
             .ok_or(())
-            .map_err(|_| error!("The map hasn't any value for the key 'geometry'"))?;
+            .map_err(|_| error!("The map hasn't any value for the key 'geometry'"))?
+            .clone();
 
-        let mut data = Map::new();
-        data.insert("geojson".to_string(), geometry.clone());
+        let source =
+            FeatureSource::from_properties(feature.get("properties").and_then(|v| v.as_object()));
 
         let feature_id = format!("{}{}", dpt, index);
+        let feature_report = FeatureReport::new(&feature_id);
 
         tasks.push(tokio::spawn(async move {
-            let result = process_feature(
-                &feature_id,
-                &id_generator_clone,
-                &api_key_clone,
-                &headers_clone,
-                data,
-                &regex_error_clone,
-            )
+            // The geometry is validated & simplified on the CPU workers, as the API responses
+            let prepared = run_blocking(&context_clone.cpu_pool, move || {
+                let mut feature_report = feature_report;
+                let geometry = prepare_geometry(&geometry, &mut feature_report);
+                (geometry, feature_report)
+            })
             .await;
+
+            let (geometry, mut feature_report) = match prepared {
+                Ok(prepared) => prepared,
+                Err(_) => {
+                    let mut feature_report = FeatureReport::new(&feature_id);
+                    feature_report.valid_geometry = false;
+                    (None, feature_report)
+                }
+            };
+
+            let result = match geometry {
+                Some(geometry) => {
+                    process_feature(
+                        &feature_id,
+                        &mut feature_report,
+                        &context_clone,
+                        geometry,
+                        source,
+                    )
+                    .await
+                }
+                None => Ok(()),
+            };
             drop(permit);
 
+            run_report_clone.add(feature_report);
+
             if result.is_err() {
                 error!("Failed to process the feature {} of dpt {}", index, dpt);
             }
@@ -266,9 +559,7 @@ async fn process_features(
     Ok(())
 }
 
-fn set_up(
-    folder_path: &str,
-) -> Result<(Vec<DirEntry>, String, HeaderMap, Regex, IdGenerator), String> {
+fn set_up(folder_path: &str) -> Result<(Vec<DirEntry>, ApiContext, RunReport), String> {
     let folder_path = PathBuf::from(folder_path);
     let target_folder = PathBuf::from(TARGET_FOLDER);
 
@@ -277,6 +568,17 @@ fn set_up(
             .map_err(|e| format!("Failed to create the folder {:?} : {}", target_folder, e))?;
     }
 
+    let quarantine_folder = PathBuf::from(QUARANTINE_FOLDER);
+
+    if !fs::exists(&quarantine_folder).unwrap_or(false) {
+        fs::create_dir_all(&quarantine_folder).map_err(|e| {
+            format!(
+                "Failed to create the folder {:?} : {}",
+                quarantine_folder, e
+            )
+        })?;
+    }
+
     let entries = fs::read_dir(&folder_path)
         .map_err(|e| format!("Failed to read the folder {:?} : {}", folder_path, e))?
         .flatten()
@@ -292,14 +594,19 @@ fn set_up(
         Regex::new(r#"403\s*:\s*\{"message":"Surface\s+(.*?)\s+du\s+GeoJSON\s+trop\s+grande"\}"#)
             .map_err(|e| format!("Failed to initiliaze the regex : {}", e))?;
 
-    let id_generator = IdGenerator::new();
+    let context = ApiContext {
+        api_key,
+        headers,
+        regex_error,
+        cpu_pool: new_cpu_pool(),
+    };
 
-    Ok((entries, api_key, headers, regex_error, id_generator))
+    Ok((entries, context, RunReport::new()))
 }
 
 /// Takes as input the folder who's contains the **GeoJSON** files from *'France GeoJSON'*.
 pub async fn main(folder_path: &str) -> Result<String, String> {
-    let (entries, api_key, headers, regex_error, id_generator) = set_up(folder_path)?;
+    let (entries, context, run_report) = set_up(folder_path)?;
 
     let mut dpt = 1usize;
     for entry in entries {
@@ -325,16 +632,9 @@ pub async fn main(folder_path: &str) -> Result<String, String> {
                     })
                     .collect::<Vec<Map<String, Value>>>();
 
-                if process_features(
-                    features,
-                    &id_generator,
-                    &api_key,
-                    &headers,
-                    dpt,
-                    &regex_error,
-                )
-                .await
-                .is_err()
+                if process_features(features, &run_report, &context, dpt)
+                    .await
+                    .is_err()
                 {
                     error!(
                         "Failed to process the features of the departement : {}",
@@ -349,5 +649,8 @@ pub async fn main(folder_path: &str) -> Result<String, String> {
         }
     }
 
+    run_report.write(&PathBuf::from(REPORT_PATH))?;
+    run_report.write_coverage(&PathBuf::from(COVERAGE_PATH))?;
+
     Ok("Successfully extract and save the Data from the API DVF+ !".to_string())
 }
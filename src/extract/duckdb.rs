@@ -7,9 +7,20 @@ use duckdb::Connection;
 use mylog::error;
 
 use crate::transform;
+use crate::transform::partition::{NULL_PARTITION, OutputLayout};
+use crate::transform::tables::Tables;
 
 const INIT_SCRIPT: &str = include_str!("../../databases/init.sql");
 const FILE_PATTERN: &str = "mutations";
+/// Join of each table of [Tables::NAMES] to its mutation, the rows are exported in the partition of their mutation.
+const PARTITION_JOINS: [&str; 6] = [
+    "JOIN Mutations m ON m.idm = t.idm",
+    "JOIN Mutations m ON m.idm = t.idm",
+    "JOIN Dispositions d ON d.idd = t.idd JOIN Mutations m ON m.idm = d.idm",
+    "JOIN Parcelles p ON p.idg = t.idg JOIN Dispositions d ON d.idd = p.idd JOIN Mutations m ON m.idm = d.idm",
+    "JOIN Parcelles p ON p.idg = t.idg JOIN Dispositions d ON d.idd = p.idd JOIN Mutations m ON m.idm = d.idm",
+    "JOIN Mutations m ON m.idm = t.idm",
+];
 
 /// Create a new connection in memory or in the specified file
 fn new_connection(db_path: Option<&str>) -> Result<Connection, ()> {
@@ -25,9 +36,12 @@ fn new_connection(db_path: Option<&str>) -> Result<Connection, ()> {
     }
 }
 
+/// Insert the rows of the Parquet files of the glob ***path***, the rows already loaded from another file
+/// (same ids, as they're derived from the business keys) are skipped.<br>
+/// The columns of the Hive partitions aren't read, they're already in the files.
 fn insert_values(conn: &Connection, path: &str, table_name: &str) -> Result<(), ()> {
     conn.execute(
-        &format!("INSERT INTO {} SELECT * FROM read_parquet('{}')",table_name, path),
+        &format!("INSERT OR IGNORE INTO {} SELECT * FROM read_parquet('{}', hive_partitioning = false)",table_name, path),
         []
     ).map_err(|e| error!("{}", e))?;
 
@@ -59,30 +73,58 @@ fn from_folder(
         let _ = fs::create_dir(&target_folder);
     }
 
-    for entry in entries {
-        let path = entry.path();
-        let filename = path.file_name().unwrap().to_string_lossy();
+    let layout = OutputLayout::from_args();
 
-        if filename.starts_with(FILE_PATTERN) && path.extension().unwrap_or_default() == "parquet" {
-            let mutations_src = path.as_os_str().to_string_lossy();
-            let classes_src = mutations_src.replace("mutations", "classes");
+    match layout {
+        OutputLayout::Flat => {
+            for entry in entries {
+                let path = entry.path();
+                let filename = path.file_name().unwrap().to_string_lossy();
 
-            // Load data from Parquet files
-            insert_values(conn, &mutations_src, "mutations")?;
-            insert_values(conn, &classes_src, "classes")?;
-        }
+                if filename.starts_with(FILE_PATTERN) && path.extension().unwrap_or_default() == "parquet" {
+                    let mutations_src = path.as_os_str().to_string_lossy();
+
+                    // Load data from Parquet files, the parent tables first
+                    for table_name in Tables::NAMES {
+                        let table_src = mutations_src.replace(FILE_PATTERN, table_name);
+                        insert_values(conn, &table_src, table_name)?;
+                    }
+                }
+            }
+        },
+        OutputLayout::Partitioned => {
+            // Load every partition of the tables, the parent tables first
+            for table_name in Tables::NAMES {
+                let table_folder = folder_path.join(table_name);
+
+                if fs::exists(&table_folder).unwrap_or(false) {
+                    let table_src = table_folder.join("**").join("*.parquet");
+                    insert_values(conn, &table_src.as_os_str().to_string_lossy(), table_name)?;
+                }
+            }
+        },
     }
 
     // Transform data
     function(conn)?;
 
-    let biding = target_folder.join("mutations.parquet");
-    let mutations_dest = biding.as_os_str().to_string_lossy();
-    let classes_dest = mutations_dest.replace("mutations", "classes");
-
     // Export transformed data
-    export_to_parquet(conn, &mutations_dest, "mutations")?;
-    export_to_parquet(conn, &classes_dest, "classes")?;
+    match layout {
+        OutputLayout::Flat => {
+            let biding = target_folder.join("mutations.parquet");
+            let mutations_dest = biding.as_os_str().to_string_lossy();
+
+            for table_name in Tables::NAMES {
+                let table_dest = mutations_dest.replace(FILE_PATTERN, table_name);
+                export_to_parquet(conn, &table_dest, table_name)?;
+            }
+        },
+        OutputLayout::Partitioned => {
+            for (table_name, join) in Tables::NAMES.into_iter().zip(PARTITION_JOINS) {
+                export_to_partitions(conn, &target_folder.join(table_name), table_name, join)?;
+            }
+        },
+    }
 
     Ok(())
 }
@@ -106,6 +148,32 @@ fn export_to_parquet(conn: &Connection, file_path: &str, table_name: &str) -> Re
     Ok(())
 }
 
+/// Export the ***table_name*** into the Hive partitions *'dep=.../year=...'* of the ***folder_path***,
+/// the department and the year come from the mutation of the rows reached by the ***join***.
+fn export_to_partitions(conn: &Connection, folder_path: &PathBuf, table_name: &str, join: &str) -> Result<(), ()> {
+    // The partitions of the previous export would be mixed with the new ones
+    if fs::exists(folder_path).unwrap_or(false) {
+        fs::remove_dir_all(folder_path).map_err(|e| error!("{}", e))?;
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "COPY (
+                SELECT t.*, coalesce(m.coddep, m.source_code_dep, '{}') AS dep, year(m.datemut) AS year
+                FROM {} t {}
+            ) TO '{}' (FORMAT PARQUET, PARTITION_BY (dep, year), FILENAME_PATTERN 'part-{{i}}')",
+            NULL_PARTITION,
+            table_name,
+            join,
+            &folder_path.as_os_str().to_string_lossy()
+        ))
+        .map_err(|e| error!("{}", e))?;
+
+    stmt.execute([]).map_err(|e| error!("{}", e))?;
+
+    Ok(())
+}
+
 pub fn main(folder_path: &str, db_path: Option<&str>) -> Result<String, String> {
     let conn =
         new_connection(db_path).map_err(|_| "Failed to create a DuckDB connection".to_string())?;
@@ -1,191 +1,812 @@
-use serde_json::{Map, Value};
+use serde::{Deserialize, Serialize};
 
-fn inside(point: &[f64; 2], split_value: f64, axis: &str, side: &str) -> bool {
-    let value: f64 = if axis == "x" { point[0] } else { point[1] };
+/// Point of a GeoJSON geometry, serialized as ***[x, y]***.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[f64; 2]", into = "[f64; 2]")]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
 
-    if ["left", "bottom"].contains(&side) {
-        value <= split_value
-    } else {
-        value >= split_value
+/// Closed ring of a Polygon : the first point is repeated at the end.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Ring(pub Vec<Point>);
+
+/// Polygon with its exterior ring first, followed by its holes.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Polygon(pub Vec<Ring>);
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MultiPolygon(pub Vec<Polygon>);
+
+/// Geometry of a GeoJSON feature, serialized as ***{"type": ..., "coordinates": ...}***.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Polygon(Polygon),
+    MultiPolygon(MultiPolygon),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    pub max_y: f64,
+}
+
+/// Axis used to split a geometry in two.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// Side of the split line : ***Lower*** is the left/bottom one and ***Upper*** the right/top one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Lower,
+    Upper,
+}
+
+impl From<[f64; 2]> for Point {
+    fn from(value: [f64; 2]) -> Self {
+        Self {
+            x: value[0],
+            y: value[1],
+        }
     }
 }
 
-fn compute_intersection(s: [f64; 2], p: [f64; 2], split_value: f64, axis: &str) -> [f64; 2] {
-    if axis == "x" {
-        let t = (split_value - s[0]) / (p[0] - s[0]);
-        let y = s[1] + t * (p[1] - s[1]);
-        [split_value, y]
-    } else {
-        let t = (split_value - s[1]) / (p[1] - s[1]);
-        let x = s[0] + t * (p[0] - s[0]);
-        [x, split_value]
+impl From<Point> for [f64; 2] {
+    fn from(value: Point) -> Self {
+        [value.x, value.y]
     }
 }
 
-fn clip_polygon(
-    polygon: &Vec<Vec<f64>>,
-    split_value: f64,
-    axis: &str,
-    side: &str,
-) -> Result<Vec<Vec<Vec<f64>>>, String> {
-    let mut result: Vec<Vec<f64>> = Vec::new();
-    let s = polygon.last().ok_or(format!(
-        "Failed to get the last element of the polygon : polygon size : {}",
-        polygon.len()
-    ))?;
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn coordinate(&self, axis: Axis) -> f64 {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+        }
+    }
+
+    fn distance(&self, other: &Point) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
 
-    let mut point_s: [f64; 2] = s[..]
-        .try_into()
-        .map_err(|_| format!("The following point hasn't the expected format : {:?}", s))?;
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Return the largest axis of the bounding box and its middle.
+    pub fn split_line(&self) -> (Axis, f64) {
+        if self.width() > self.height() {
+            (Axis::X, (self.min_x + self.max_x) / 2f64)
+        } else {
+            (Axis::Y, (self.min_y + self.max_y) / 2f64)
+        }
+    }
+
+    fn extend(&mut self, other: &BoundingBox) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.max_x = self.max_x.max(other.max_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+}
+
+impl Ring {
+    pub fn points(&self) -> &[Point] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.len() > 1 && self.0.first() == self.0.last()
+    }
 
-    for point in polygon {
-        let point: [f64; 2] = point[..].try_into().map_err(|_| {
-            format!(
-                "The following point hasn't the expected format : {:?}",
-                point
-            )
-        })?;
+    /// Return the signed area of the ring (**positive** when counterclockwise).
+    pub fn signed_area(&self) -> f64 {
+        self.0
+            .windows(2)
+            .map(|pair| pair[0].x * pair[1].y - pair[1].x * pair[0].y)
+            .sum::<f64>()
+            / 2f64
+    }
+
+    /// Return the signed area and the first moments (***x***, ***y***) of the ring.
+    fn moments(&self) -> (f64, f64, f64) {
+        self.0
+            .windows(2)
+            .fold((0f64, 0f64, 0f64), |(area, mx, my), pair| {
+                let cross = pair[0].x * pair[1].y - pair[1].x * pair[0].y;
+                (
+                    area + cross / 2f64,
+                    mx + (pair[0].x + pair[1].x) * cross / 6f64,
+                    my + (pair[0].y + pair[1].y) * cross / 6f64,
+                )
+            })
+    }
+
+    /// Return the **min** & **max** values for the X and Y axis.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let first = self.0.first()?;
+        let mut bbox = BoundingBox {
+            min_x: first.x,
+            max_x: first.x,
+            min_y: first.y,
+            max_y: first.y,
+        };
 
-        if inside(&point, split_value, axis, side) {
+        for point in &self.0 {
+            bbox.min_x = bbox.min_x.min(point.x);
+            bbox.max_x = bbox.max_x.max(point.x);
+            bbox.min_y = bbox.min_y.min(point.y);
+            bbox.max_y = bbox.max_y.max(point.y);
+        }
+        Some(bbox)
+    }
+}
+
+impl Polygon {
+    pub fn exterior(&self) -> Option<&Ring> {
+        self.0.first()
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.exterior()?.bounding_box()
+    }
+
+    /// Return the area and the first moments of the polygon, whatever the winding order of its rings.
+    fn moments(&self) -> (f64, f64, f64) {
+        self.0
+            .iter()
+            .enumerate()
+            .fold((0f64, 0f64, 0f64), |(area, mx, my), (index, ring)| {
+                let (ring_area, ring_mx, ring_my) = ring.moments();
+                // The exterior ring is added and the holes are removed
+                let sign = ring_area.signum() * if index == 0 { 1f64 } else { -1f64 };
+                (
+                    area + sign * ring_area,
+                    mx + sign * ring_mx,
+                    my + sign * ring_my,
+                )
+            })
+    }
+}
+
+impl MultiPolygon {
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut polygons = self.0.iter().flat_map(|polygon| polygon.bounding_box());
+        let mut bbox = polygons.next()?;
+        polygons.for_each(|other| bbox.extend(&other));
+        Some(bbox)
+    }
+}
+
+impl Geometry {
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        match self {
+            Geometry::Polygon(polygon) => polygon.bounding_box(),
+            Geometry::MultiPolygon(multi_polygon) => multi_polygon.bounding_box(),
+        }
+    }
+
+    pub fn polygons(&self) -> &[Polygon] {
+        match self {
+            Geometry::Polygon(polygon) => std::slice::from_ref(polygon),
+            Geometry::MultiPolygon(multi_polygon) => &multi_polygon.0[..],
+        }
+    }
+
+    /// Return the number of points of the geometry.
+    pub fn size(&self) -> usize {
+        self.polygons()
+            .iter()
+            .flat_map(|polygon| &polygon.0)
+            .map(|ring| ring.len())
+            .sum()
+    }
+
+    /// Return the area-weighted centroid of the geometry.<br>
+    /// Fall back on the center of the bounding box when the area is null.
+    pub fn centroid(&self) -> Option<Point> {
+        let (area, mx, my) = self
+            .polygons()
+            .iter()
+            .map(|polygon| polygon.moments())
+            .fold(
+                (0f64, 0f64, 0f64),
+                |(area, mx, my), (polygon_area, polygon_mx, polygon_my)| {
+                    (area + polygon_area, mx + polygon_mx, my + polygon_my)
+                },
+            );
+
+        if area != 0f64 {
+            Some(Point::new(mx / area, my / area))
+        } else {
+            let bbox = self.bounding_box()?;
+            Some(Point::new(
+                (bbox.min_x + bbox.max_x) / 2f64,
+                (bbox.min_y + bbox.max_y) / 2f64,
+            ))
+        }
+    }
+
+    /// Encode the geometry as little-endian ***WKB*** (Well-Known Binary).
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let mut wkb: Vec<u8> = Vec::with_capacity(9 + self.size() * 16);
+
+        match self {
+            Geometry::Polygon(polygon) => write_wkb_polygon(polygon, &mut wkb),
+            Geometry::MultiPolygon(multi_polygon) => {
+                wkb.push(1u8);
+                wkb.extend_from_slice(&6u32.to_le_bytes());
+                wkb.extend_from_slice(&(multi_polygon.0.len() as u32).to_le_bytes());
+                for polygon in &multi_polygon.0 {
+                    write_wkb_polygon(polygon, &mut wkb);
+                }
+            }
+        }
+        wkb
+    }
+}
+
+fn write_wkb_polygon(polygon: &Polygon, wkb: &mut Vec<u8>) {
+    wkb.push(1u8);
+    wkb.extend_from_slice(&3u32.to_le_bytes());
+    wkb.extend_from_slice(&(polygon.0.len() as u32).to_le_bytes());
+
+    for ring in &polygon.0 {
+        wkb.extend_from_slice(&(ring.len() as u32).to_le_bytes());
+        for point in ring.points() {
+            wkb.extend_from_slice(&point.x.to_le_bytes());
+            wkb.extend_from_slice(&point.y.to_le_bytes());
+        }
+    }
+}
+
+fn inside(point: &Point, split_value: f64, axis: Axis, side: Side) -> bool {
+    let value = point.coordinate(axis);
+
+    match side {
+        Side::Lower => value <= split_value,
+        Side::Upper => value >= split_value,
+    }
+}
+
+fn compute_intersection(s: Point, p: Point, split_value: f64, axis: Axis) -> Point {
+    match axis {
+        Axis::X => {
+            let t = (split_value - s.x) / (p.x - s.x);
+            Point::new(split_value, s.y + t * (p.y - s.y))
+        }
+        Axis::Y => {
+            let t = (split_value - s.y) / (p.y - s.y);
+            Point::new(s.x + t * (p.x - s.x), split_value)
+        }
+    }
+}
+
+fn clip_polygon(ring: &Ring, split_value: f64, axis: Axis, side: Side) -> Result<Polygon, String> {
+    let mut result: Vec<Point> = Vec::new();
+    let mut point_s = *ring.0.last().ok_or(format!(
+        "Failed to get the last element of the polygon : polygon size : {}",
+        ring.len()
+    ))?;
+
+    for point in ring.points() {
+        if inside(point, split_value, axis, side) {
             if !inside(&point_s, split_value, axis, side) {
-                let intersection = compute_intersection(point_s, point, split_value, axis);
-                result.push(intersection.to_vec());
+                result.push(compute_intersection(point_s, *point, split_value, axis));
             }
-            result.push(point.to_vec());
+            result.push(*point);
         } else if inside(&point_s, split_value, axis, side) {
-            let intersection = compute_intersection(point_s, point, split_value, axis);
-            result.push(intersection.to_vec());
+            result.push(compute_intersection(point_s, *point, split_value, axis));
         }
-        point_s = point;
+        point_s = *point;
     }
 
     // Close the Polygon if necessary
-    if result[0] != result[result.len() - 1] {
-        result.push(result[0].clone())
+    if let (Some(first), Some(last)) = (result.first().copied(), result.last())
+        && first != *last
+    {
+        result.push(first)
     }
 
     if result.len() >= 4 {
-        Ok(vec![result])
+        Ok(Polygon(vec![Ring(result)]))
     } else {
         Err("Inconsistant size : The Polygon need to has 4 points or more.".to_string())
     }
 }
 
-/// Safe way to unwrap the coordinates from the GeoJSON format.
-pub fn unwrap_coordinates(value: &[Value]) -> Vec<Vec<Vec<f64>>> {
-    value
-        .iter()
-        .flat_map(|value1| {
-            if let Value::Array(vec1) = value1 {
-                Ok(vec1
-                    .iter()
-                    .flat_map(|value2| {
-                        if let Value::Array(vec2) = value2 {
-                            Ok(vec2
-                                .iter()
-                                .flat_map(|value3| {
-                                    if let Value::Number(nb) = value3 {
-                                        nb.as_f64().ok_or(())
-                                    } else {
-                                        Err(())
-                                    }
-                                })
-                                .collect::<Vec<f64>>())
-                        } else {
-                            Err(())
-                        }
-                    })
-                    .collect::<Vec<Vec<f64>>>())
-            } else {
-                Err(())
-            }
-        })
-        .collect::<Vec<Vec<Vec<f64>>>>()
+/// Split the geometry extracted from the GeoJSON object into two geometry.<br>
+/// It's splitted with the **X** or **Y** axis depending on the largest.
+pub fn split_geometry(geometry: &Geometry) -> Result<(Geometry, Geometry), String> {
+    let Geometry::Polygon(polygon) = geometry else {
+        return Err(
+            "Inconsistant geometry type, this function only support Polygon type.".to_string(),
+        );
+    };
+
+    let exterior = polygon
+        .exterior()
+        .ok_or("Inconsistant geometry : Empty coordinates.")?;
+
+    let (axis, split_value) = exterior
+        .bounding_box()
+        .ok_or("Inconsistant coordinates : Empty vector.")?
+        .split_line();
+
+    let poly1 = clip_polygon(exterior, split_value, axis, Side::Lower)?;
+    let poly2 = clip_polygon(exterior, split_value, axis, Side::Upper)?;
+
+    Ok((Geometry::Polygon(poly1), Geometry::Polygon(poly2)))
 }
 
-/// Return the **min** & **max** values for the X and Y axis.
-fn min_max_coordinate(coordinates: &Vec<Vec<f64>>) -> Result<(f64, f64, f64, f64), String> {
-    let mut min_x = f64::INFINITY;
-    let mut max_x = f64::NEG_INFINITY;
-    let mut min_y = f64::INFINITY;
-    let mut max_y = f64::NEG_INFINITY;
+/// Return the distance between the ***point*** and the segment [***start***, ***end***].
+fn segment_distance(point: Point, start: Point, end: Point) -> f64 {
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let length = dx * dx + dy * dy;
 
-    if coordinates.is_empty() {
-        return Err("Inconsistant coordinates : Empty vector.".to_string());
+    if length == 0f64 {
+        return point.distance(&start);
     }
 
-    for point in coordinates {
-        let x = *point
-            .first()
-            .ok_or("Inconsistant coordinate : Empty point.")?;
+    let t = (((point.x - start.x) * dx + (point.y - start.y) * dy) / length).clamp(0f64, 1f64);
+    point.distance(&Point::new(start.x + t * dx, start.y + t * dy))
+}
+
+/// Simplify the ***points*** with the **Douglas-Peucker** algorithm.<br>
+/// Every removed point is at a distance lower than ***tolerance*** of the result.
+fn douglas_peucker(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
 
-        let y = *point
-            .get(1)
-            .ok_or("Inconsistant coordinate : The point is not 2D.")?;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
 
-        if min_x > x {
-            min_x = x;
-        } else if max_x < x {
-            max_x = x
+    let mut stack: Vec<(usize, usize)> = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_distance = 0f64;
+        let mut max_index = first;
+
+        for index in first + 1..last {
+            let distance = segment_distance(points[index], points[first], points[last]);
+            if distance > max_distance {
+                max_distance = distance;
+                max_index = index;
+            }
         }
 
-        if min_y > y {
-            min_y = y;
-        } else if max_y < y {
-            max_y = y
+        if max_distance > tolerance {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
         }
     }
 
-    Ok((min_x, max_x, min_y, max_y))
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(point, _)| *point)
+        .collect()
 }
 
-/// Split the geometry extracted from the GeoJSON object and split into two geometry.<br>
-/// It's splitted with the **X** or **Y** axis depending on the largest.
-pub fn split_geometry(geometry: &Value) -> Result<(Value, Value), String> {
-    let geometry = geometry
-        .as_object()
-        .ok_or("Inconsistant Value in input. The input need to be a Value::Object().")?;
+/// Return +1 to offset the edges of the ***ring*** on their right, -1 on their left.<br>
+/// The ring grows when ***outward*** is true, otherwise it shrinks.
+fn offset_side(ring: &Ring, outward: bool) -> f64 {
+    let side = if ring.signed_area() > 0f64 {
+        1f64
+    } else {
+        -1f64
+    };
+    if outward { side } else { -side }
+}
 
-    if geometry.get("type").unwrap_or_default() != "Polygon" {
-        return Err(
-            "Inconsistant geometry type, this function only support Polygon type.".to_string(),
+/// Corner of a ring, with the unit directions of its edges and their normals on the side of the offset.
+struct Corner {
+    point: Point,
+    t1: Point,
+    t2: Point,
+    n1: Point,
+    n2: Point,
+    /// Positive on a convex corner for the side of the offset, negative on a reflex one.
+    turn: f64,
+    /// 1 + cosine of the angle between the normals : the miter is at ***distance / dot*** of the bisector.
+    dot: f64,
+}
+
+impl Corner {
+    /// Build the corner of the ***index***-th point of the open ring ***points***.
+    fn new(points: &[Point], index: usize, side: f64) -> Self {
+        let size = points.len();
+        let previous = points[(index + size - 1) % size];
+        let point = points[index];
+        let next = points[(index + 1) % size];
+
+        let direction = |from: Point, to: Point| -> Point {
+            let length = from.distance(&to);
+            Point::new((to.x - from.x) / length, (to.y - from.y) / length)
+        };
+
+        let t1 = direction(previous, point);
+        let t2 = direction(point, next);
+        let n1 = Point::new(side * t1.y, -side * t1.x);
+        let n2 = Point::new(side * t2.y, -side * t2.x);
+
+        Self {
+            point,
+            t1,
+            t2,
+            n1,
+            n2,
+            turn: (t1.x * t2.y - t1.y * t2.x) * side,
+            dot: 1f64 + n1.x * n2.x + n1.y * n2.y,
+        }
+    }
+
+    /// Return the length of each edge overlapped by the miter of a reflex corner offset by ***distance***,
+    /// the convex corners extend their edges instead.
+    fn overlap(&self, distance: f64) -> f64 {
+        if self.turn < 0f64 {
+            distance * -self.turn / self.dot
+        } else {
+            0f64
+        }
+    }
+}
+
+/// Return ***true*** if the ***point*** is inside or on the triangle (***a***, ***b***, ***c***).
+fn in_triangle(point: Point, a: Point, b: Point, c: Point) -> bool {
+    let cross = |from: Point, to: Point| -> f64 {
+        (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+    };
+    let (ab, bc, ca) = (cross(a, b), cross(b, c), cross(c, a));
+
+    (ab >= 0f64 && bc >= 0f64 && ca >= 0f64) || (ab <= 0f64 && bc <= 0f64 && ca <= 0f64)
+}
+
+/// Remove the reflex corners of the closed ***ring*** whose miters would overlap a whole edge once offset
+/// by ***distance***, the offset ring would cross itself there (e.g. a notch narrower than twice the offset).<br>
+/// Removing a reflex corner only grows the ring on the side of the offset, so the offset ring still covers it.<br>
+/// Return an Error if a corner can't be removed without crossing another edge of the ring.
+fn remove_overlapping_corners(ring: &Ring, distance: f64, outward: bool) -> Result<Ring, String> {
+    let side = offset_side(ring, outward);
+    // The first point is repeated at the end of a closed ring
+    let mut points = ring.0[..ring.len() - 1].to_vec();
+
+    while points.len() > 3 {
+        let size = points.len();
+        let overlaps = (0..size)
+            .map(|index| Corner::new(&points, index, side).overlap(distance))
+            .collect::<Vec<f64>>();
+
+        let Some(index) = (0..size).find(|&index| {
+            let next = (index + 1) % size;
+            overlaps[index] + overlaps[next] >= points[index].distance(&points[next])
+        }) else {
+            break;
+        };
+
+        // The sharpest corner of the edge is removed, it's always a reflex one
+        let next = (index + 1) % size;
+        let index = if overlaps[index] >= overlaps[next] {
+            index
+        } else {
+            next
+        };
+
+        let (previous, point, next) = (
+            points[(index + size - 1) % size],
+            points[index],
+            points[(index + 1) % size],
         );
+        if points.iter().any(|other| {
+            ![previous, point, next].contains(other) && in_triangle(*other, previous, point, next)
+        }) {
+            return Err(
+                "Inconsistant geometry : A reflex corner of the ring can't be removed.".to_string(),
+            );
+        }
+
+        points.remove(index);
     }
 
-    let coordinates = geometry
-        .get("coordinates")
-        .ok_or("Inconsistant geometry : The key 'coordinates' doesn't exists.")?
-        .as_array()
-        .ok_or("Inconsistant geometry : The key 'coordinates' is not an Array<Value>.")?;
-    let coordinates = unwrap_coordinates(coordinates);
+    points.push(points[0]);
+    Ok(Ring(points))
+}
 
-    let exterior = coordinates
-        .first()
-        .ok_or("Inconsistant geometry : Empty coordinates.")?;
+/// Offset every edge of the closed ***ring*** by ***distance***.<br>
+/// The ring grows when ***outward*** is true, otherwise it shrinks.<br>
+/// Sharp convex corners use a square join so the result always contains the round buffer,
+/// the reflex corners use a miter join and must not overlap their edges (see [remove_overlapping_corners]).
+fn offset_ring(ring: &Ring, distance: f64, outward: bool) -> Ring {
+    let side = offset_side(ring, outward);
+    // The first point is repeated at the end of a closed ring
+    let points = &ring.0[..ring.len() - 1];
+
+    let mut result: Vec<Point> = Vec::new();
+    for index in 0..points.len() {
+        let Corner {
+            point,
+            t1,
+            t2,
+            n1,
+            n2,
+            turn,
+            dot,
+        } = Corner::new(points, index, side);
 
-    let (min_x, max_x, min_y, max_y) = min_max_coordinate(exterior)?;
-    let width = max_x - min_x;
-    let height = max_y - min_y;
+        if turn > 0f64 && dot < 0.5 {
+            result.push(Point::new(
+                point.x + distance * (n1.x + t1.x),
+                point.y + distance * (n1.y + t1.y),
+            ));
+            result.push(Point::new(
+                point.x + distance * (n2.x - t2.x),
+                point.y + distance * (n2.y - t2.y),
+            ));
+        } else if dot > f64::EPSILON {
+            result.push(Point::new(
+                point.x + distance * (n1.x + n2.x) / dot,
+                point.y + distance * (n1.y + n2.y) / dot,
+            ));
+        }
+    }
 
-    let poly1: Vec<Vec<Vec<f64>>>;
-    let poly2: Vec<Vec<Vec<f64>>>;
-    if width > height {
-        let split_value = (min_x + max_x) / 2f64;
-        poly1 = clip_polygon(exterior, split_value, "x", "left")?;
-        poly2 = clip_polygon(exterior, split_value, "x", "right")?;
+    if let Some(first) = result.first().copied() {
+        result.push(first);
+    }
+    Ring(result)
+}
+
+/// Simplify one ring of a Polygon and buffer it by ***tolerance*** so the covered surface is never reduced.<br>
+/// Return ***None*** when the ring disappears (e.g. a hole smaller than the buffer).
+fn simplify_ring(ring: &Ring, tolerance: f64, exterior: bool) -> Result<Option<Ring>, String> {
+    let mut points = ring.0.clone();
+    points.dedup();
+
+    if points.len() < 4 {
+        return Err("Inconsistant size : The Polygon need to has 4 points or more.".to_string());
+    }
+
+    let mut simplified = Ring(douglas_peucker(&points, tolerance));
+    if simplified.len() < 4 || simplified.signed_area().abs() <= f64::EPSILON {
+        simplified = Ring(points);
+    }
+
+    let simplified = match remove_overlapping_corners(&simplified, tolerance, exterior) {
+        Ok(simplified) => simplified,
+        Err(message) if exterior => return Err(message),
+        // Without the hole the polygon still covers the original one
+        Err(_) => return Ok(None),
+    };
+
+    let buffered = offset_ring(&simplified, tolerance, exterior);
+
+    if buffered.len() < 4 || buffered.signed_area().signum() != simplified.signed_area().signum() {
+        if exterior {
+            return Err(
+                "Inconsistant geometry : The buffered exterior ring is degenerated.".to_string(),
+            );
+        }
+        return Ok(None);
+    }
+
+    Ok(Some(buffered))
+}
+
+fn simplify_polygon(polygon: &Polygon, tolerance: f64) -> Result<Polygon, String> {
+    let mut result: Vec<Ring> = Vec::new();
+
+    for (index, ring) in polygon.0.iter().enumerate() {
+        if let Some(ring) = simplify_ring(ring, tolerance, index == 0)? {
+            result.push(ring);
+        }
+    }
+
+    if result.is_empty() {
+        Err("Inconsistant geometry : Empty coordinates.".to_string())
     } else {
-        let split_value = (min_y + max_y) / 2f64;
-        poly1 = clip_polygon(exterior, split_value, "y", "bottom")?;
-        poly2 = clip_polygon(exterior, split_value, "y", "top")?;
+        Ok(Polygon(result))
+    }
+}
+
+/// Simplify the geometry extracted from the GeoJSON object to reduce the size of the requests.<br>
+/// The rings are simplified with the **Douglas-Peucker** algorithm and then buffered outward by ***tolerance***,
+/// so the resulting geometry always covers the original one.
+pub fn simplify_geometry(geometry: &Geometry, tolerance: f64) -> Result<Geometry, String> {
+    match geometry {
+        Geometry::Polygon(polygon) => Ok(Geometry::Polygon(simplify_polygon(polygon, tolerance)?)),
+        Geometry::MultiPolygon(multi_polygon) => Ok(Geometry::MultiPolygon(MultiPolygon(
+            multi_polygon
+                .0
+                .iter()
+                .map(|polygon| simplify_polygon(polygon, tolerance))
+                .collect::<Result<Vec<Polygon>, String>>()?,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::extract::api_dvf::SearchRequest;
+    use crate::extract::validation::is_valid;
+
+    fn polygon() -> Value {
+        json!({
+            "type": "Polygon",
+            "coordinates": [[[2.35, 48.85], [2.36, 48.85], [2.36, 48.86], [2.35, 48.86], [2.35, 48.85]]]
+        })
     }
 
-    let mut geometry1 = Map::new();
-    geometry1.insert("type".to_string(), "Polygon".into());
-    geometry1.insert("coordinates".to_string(), poly1.into());
+    fn multi_polygon() -> Value {
+        json!({
+            "type": "MultiPolygon",
+            "coordinates": [
+                [
+                    [[2.35, 48.85], [2.37, 48.85], [2.37, 48.87], [2.35, 48.87], [2.35, 48.85]],
+                    [[2.355, 48.855], [2.355, 48.865], [2.365, 48.865], [2.365, 48.855], [2.355, 48.855]]
+                ],
+                [[[-1.5, 47.2], [-1.4, 47.2], [-1.4, 47.3], [-1.5, 47.2]]]
+            ]
+        })
+    }
 
-    let mut geometry2 = Map::new();
-    geometry2.insert("type".to_string(), "Polygon".into());
-    geometry2.insert("coordinates".to_string(), poly2.into());
+    /// The geometry sent to the API must be the GeoJSON it was parsed from.
+    fn assert_round_trip(value: Value) -> Geometry {
+        let geometry: Geometry = serde_json::from_value(value.clone()).unwrap();
 
-    Ok((geometry1.into(), geometry2.into()))
+        assert_eq!(serde_json::to_value(&geometry).unwrap(), value);
+        assert_eq!(
+            serde_json::to_value(SearchRequest { geojson: &geometry }).unwrap(),
+            json!({ "geojson": value })
+        );
+
+        geometry
+    }
+
+    #[test]
+    fn polygon_round_trip() {
+        let geometry = assert_round_trip(polygon());
+
+        match geometry {
+            Geometry::Polygon(Polygon(rings)) => {
+                assert_eq!(rings.len(), 1);
+                assert_eq!(rings[0].len(), 5);
+                assert_eq!(rings[0].points()[1], Point::new(2.36, 48.85));
+            }
+            other => panic!("Expected a Polygon, got {:?}", other),
+        }
+    }
+
+    /// Return ***true*** if the ***point*** is strictly inside the geometry (even-odd rule over its rings).
+    fn contains(geometry: &Geometry, point: Point) -> bool {
+        geometry.polygons().iter().any(|polygon| {
+            polygon
+                .0
+                .iter()
+                .filter(|ring| {
+                    ring.points().windows(2).fold(false, |inside, pair| {
+                        let (a, b) = (pair[0], pair[1]);
+                        if (a.y > point.y) != (b.y > point.y)
+                            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+                        {
+                            !inside
+                        } else {
+                            inside
+                        }
+                    })
+                })
+                .count()
+                % 2
+                == 1
+        })
+    }
+
+    /// The simplified geometry must be valid and cover every vertex of the original one.
+    fn assert_covers(points: Vec<[f64; 2]>, tolerance: f64) -> Geometry {
+        let original = Geometry::Polygon(Polygon(vec![Ring(
+            points.into_iter().map(Point::from).collect(),
+        )]));
+        let simplified = simplify_geometry(&original, tolerance).unwrap();
+
+        assert!(is_valid(&simplified), "Invalid geometry {:?}", simplified);
+        for point in original.polygons()[0].0[0].points() {
+            assert!(
+                contains(&simplified, *point),
+                "{:?} isn't covered by {:?}",
+                point,
+                simplified
+            );
+        }
+
+        simplified
+    }
+
+    #[test]
+    fn simplified_concave_polygon_covers_the_original() {
+        // U shape with a noisy bottom edge, the notch is narrower than twice the buffer
+        let mut points = vec![[0.0, 0.0]];
+        points.extend(
+            (1..20).map(|index| [index as f64 * 0.5, if index % 2 == 0 { 0.2 } else { -0.2 }]),
+        );
+        points.extend([
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [6.0, 10.0],
+            [6.0, 3.0],
+            [4.0, 3.0],
+            [4.0, 10.0],
+            [0.0, 10.0],
+            [0.0, 0.0],
+        ]);
+
+        let simplified = assert_covers(points.clone(), 0.5);
+        assert!(simplified.size() < points.len());
+
+        assert_covers(points, 1.2);
+    }
+
+    #[test]
+    fn simplified_sharp_spikes_cover_the_original() {
+        // Square with a sharp spike outward and a sharp crack inward
+        let points = vec![
+            [0.0, 0.0],
+            [10.0, 0.0],
+            [10.0, 10.0],
+            [7.1, 10.0],
+            [7.0, 30.0],
+            [6.9, 10.0],
+            [3.1, 10.0],
+            [3.0, 2.0],
+            [2.9, 10.0],
+            [0.0, 10.0],
+            [0.0, 0.0],
+        ];
+
+        assert_covers(points.clone(), 0.05);
+        assert_covers(points, 0.5);
+    }
+
+    #[test]
+    fn multi_polygon_round_trip() {
+        let geometry = assert_round_trip(multi_polygon());
+
+        match geometry {
+            Geometry::MultiPolygon(MultiPolygon(polygons)) => {
+                assert_eq!(polygons.len(), 2);
+                assert_eq!(polygons[0].0.len(), 2);
+                assert_eq!(polygons[1].0[0].points()[0], Point::new(-1.5, 47.2));
+            }
+            other => panic!("Expected a MultiPolygon, got {:?}", other),
+        }
+    }
 }
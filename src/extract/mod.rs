@@ -1,4 +1,5 @@
 pub mod api_dvf;
 pub mod duckdb;
-mod geometry;
-pub mod utils;
+pub mod geometry;
+pub mod report;
+mod validation;
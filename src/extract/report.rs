@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use super::geometry::Geometry;
+use super::validation::GeometryIssue;
+use crate::transform::tables::ClassRejection;
+
+pub const REPORT_PATH: &str = "data/DVF/report.json";
+pub const COVERAGE_PATH: &str = "data/DVF/coverage.geojson";
+
+/// Summary of the extraction of one feature.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureReport {
+    pub feature_id: String,
+    pub valid_geometry: bool,
+    pub geometry_issues: Vec<GeometryIssue>,
+    pub duplicates_removed: usize,
+    pub quarantined: usize,
+    pub classes_rejected: BTreeMap<ClassRejection, usize>,
+    #[serde(skip)]
+    pub tiles: Vec<TileReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileStatus {
+    Pending,
+    Queried,
+    Empty,
+    Split,
+    Failed,
+}
+
+/// Sub-geometry of a feature sent to the API.
+#[derive(Debug, Clone)]
+pub struct TileReport {
+    pub geometry: Geometry,
+    pub depth: usize,
+    pub status: TileStatus,
+    pub rows: usize,
+    pub attempts: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct TileProperties<'a> {
+    feature_id: &'a str,
+    depth: usize,
+    status: TileStatus,
+    rows: usize,
+    attempts: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct TileFeature<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    geometry: &'a Geometry,
+    properties: TileProperties<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct TileCollection<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    features: Vec<TileFeature<'a>>,
+}
+
+/// Totals of the run over every feature.
+#[derive(Debug, Default, Serialize)]
+struct RunSummary {
+    features: usize,
+    invalid_geometries: usize,
+    duplicates_removed: usize,
+    quarantined: usize,
+    classes_rejected: BTreeMap<ClassRejection, usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunContent<'a> {
+    summary: RunSummary,
+    features: &'a [FeatureReport],
+}
+
+impl FeatureReport {
+    pub fn new(feature_id: &str) -> Self {
+        Self {
+            feature_id: feature_id.to_string(),
+            valid_geometry: true,
+            geometry_issues: Vec::new(),
+            duplicates_removed: 0,
+            quarantined: 0,
+            classes_rejected: BTreeMap::new(),
+            tiles: Vec::new(),
+        }
+    }
+}
+
+impl TileReport {
+    pub fn new(geometry: Geometry, depth: usize) -> Self {
+        Self {
+            geometry,
+            depth,
+            status: TileStatus::Pending,
+            rows: 0,
+            attempts: 0,
+        }
+    }
+}
+
+/// Collect the reports of the features processed during the run.
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    features: Arc<Mutex<Vec<FeatureReport>>>,
+}
+
+impl RunReport {
+    pub fn new() -> Self {
+        Self {
+            features: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn add(&self, report: FeatureReport) {
+        if let Ok(mut features) = self.features.lock() {
+            features.push(report);
+        }
+    }
+
+    /// Save the run report as JSON in the ***path***.
+    pub fn write(&self, path: &PathBuf) -> Result<(), String> {
+        let features = self
+            .features
+            .lock()
+            .map_err(|e| format!("Failed to lock the run report : {}", e))?;
+
+        let mut summary = RunSummary::default();
+        for feature in features.iter() {
+            summary.features += 1;
+            summary.invalid_geometries += usize::from(!feature.valid_geometry);
+            summary.duplicates_removed += feature.duplicates_removed;
+            summary.quarantined += feature.quarantined;
+            for (reason, count) in &feature.classes_rejected {
+                *summary.classes_rejected.entry(*reason).or_default() += count;
+            }
+        }
+
+        let content = serde_json::to_string_pretty(&RunContent {
+            summary,
+            features: &features,
+        })
+        .map_err(|e| format!("Failed to serialize the run report : {}", e))?;
+
+        write_file(path, &content)
+    }
+
+    /// Save every tile queried during the run as a GeoJSON FeatureCollection in the ***path***.
+    pub fn write_coverage(&self, path: &PathBuf) -> Result<(), String> {
+        let features = self
+            .features
+            .lock()
+            .map_err(|e| format!("Failed to lock the run report : {}", e))?;
+
+        let collection = TileCollection {
+            kind: "FeatureCollection",
+            features: features
+                .iter()
+                .flat_map(|feature| {
+                    feature.tiles.iter().map(|tile| TileFeature {
+                        kind: "Feature",
+                        geometry: &tile.geometry,
+                        properties: TileProperties {
+                            feature_id: &feature.feature_id,
+                            depth: tile.depth,
+                            status: tile.status,
+                            rows: tile.rows,
+                            attempts: tile.attempts,
+                        },
+                    })
+                })
+                .collect(),
+        };
+
+        let content = serde_json::to_string(&collection)
+            .map_err(|e| format!("Failed to serialize the coverage map : {}", e))?;
+
+        write_file(path, &content)
+    }
+}
+
+fn write_file(path: &PathBuf, content: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open the file {:?} : {}", path, e))?;
+
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write the file {:?} : {}", path, e))
+}
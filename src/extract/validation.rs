@@ -0,0 +1,273 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use super::geometry::{Geometry, MultiPolygon, Point, Polygon, Ring};
+
+/// Issue found in a ring of the geometry of a feature.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeometryIssue {
+    pub polygon: usize,
+    pub ring: usize,
+    pub kind: IssueKind,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    UnsupportedType { geometry_type: String },
+    MalformedRing,
+    MalformedPoint { index: usize },
+    ExtraDimensions { index: usize },
+    DuplicateVertex { index: usize },
+    UnclosedRing,
+    TooFewPoints { size: usize },
+    EmptyArea,
+    WrongWindingOrder,
+    SelfIntersection { first: usize, second: usize },
+}
+
+fn orientation(a: Point, b: Point, c: Point) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// Return ***true*** if ***point*** is in the bounding box of the segment [***a***, ***b***].
+fn on_segment(a: Point, b: Point, point: Point) -> bool {
+    point.x >= a.x.min(b.x)
+        && point.x <= a.x.max(b.x)
+        && point.y >= a.y.min(b.y)
+        && point.y <= a.y.max(b.y)
+}
+
+fn segments_intersect(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+
+    if o1 * o2 < 0f64 && o3 * o4 < 0f64 {
+        return true;
+    }
+
+    (o1 == 0f64 && on_segment(a, b, c))
+        || (o2 == 0f64 && on_segment(a, b, d))
+        || (o3 == 0f64 && on_segment(c, d, a))
+        || (o4 == 0f64 && on_segment(c, d, b))
+}
+
+/// Return the indexes of the first pair of non-adjacent edges of the closed ***ring*** that intersect.
+fn find_self_intersection(ring: &Ring) -> Option<(usize, usize)> {
+    let ring = ring.points();
+    let edges = ring.len() - 1;
+
+    for first in 0..edges {
+        let (a, b) = (ring[first], ring[first + 1]);
+
+        for second in first + 2..edges {
+            // The last edge is adjacent to the first one
+            if first == 0 && second == edges - 1 {
+                continue;
+            }
+
+            let (c, d) = (ring[second], ring[second + 1]);
+            if a.x.max(b.x) < c.x.min(d.x)
+                || c.x.max(d.x) < a.x.min(b.x)
+                || a.y.max(b.y) < c.y.min(d.y)
+                || c.y.max(d.y) < a.y.min(b.y)
+            {
+                continue;
+            }
+
+            if segments_intersect(a, b, c, d) {
+                return Some((first, second));
+            }
+        }
+    }
+    None
+}
+
+/// Validate one ring and repair what can safely be repaired.<br>
+/// Return ***None*** if the ring can't be sent to the API.
+fn validate_ring(
+    value: &Value,
+    polygon: usize,
+    ring: usize,
+    issues: &mut Vec<GeometryIssue>,
+) -> Option<Ring> {
+    let mut report = |kind: IssueKind, repaired: bool| {
+        issues.push(GeometryIssue {
+            polygon,
+            ring,
+            kind,
+            repaired,
+        })
+    };
+
+    let Some(values) = value.as_array() else {
+        report(IssueKind::MalformedRing, false);
+        return None;
+    };
+
+    let mut valid = true;
+    let mut points: Vec<Point> = Vec::new();
+
+    for (index, point) in values.iter().enumerate() {
+        let coordinates = point
+            .as_array()
+            .map(|vec| vec.iter().map(|v| v.as_f64()).collect::<Option<Vec<f64>>>());
+
+        match coordinates {
+            Some(Some(coordinates)) if coordinates.len() >= 2 => {
+                if coordinates.len() > 2 {
+                    report(IssueKind::ExtraDimensions { index }, true);
+                }
+                points.push(Point::new(coordinates[0], coordinates[1]));
+            }
+            _ => {
+                report(IssueKind::MalformedPoint { index }, false);
+                valid = false;
+            }
+        }
+    }
+
+    let mut result: Vec<Point> = Vec::new();
+    for (index, point) in points.into_iter().enumerate() {
+        if result.last() == Some(&point) {
+            report(IssueKind::DuplicateVertex { index }, true);
+        } else {
+            result.push(point);
+        }
+    }
+
+    if let (Some(first), Some(last)) = (result.first().copied(), result.last())
+        && first != *last
+    {
+        report(IssueKind::UnclosedRing, true);
+        result.push(first);
+    }
+
+    if result.len() < 4 {
+        report(IssueKind::TooFewPoints { size: result.len() }, false);
+        return None;
+    }
+
+    let mut result = Ring(result);
+    let area = result.signed_area();
+    if area == 0f64 {
+        report(IssueKind::EmptyArea, false);
+        return None;
+    }
+
+    // RFC 7946 : the exterior ring is counterclockwise and the holes are clockwise
+    if (ring == 0) != (area > 0f64) {
+        report(IssueKind::WrongWindingOrder, true);
+        result.0.reverse();
+    }
+
+    if let Some((first, second)) = find_self_intersection(&result) {
+        report(IssueKind::SelfIntersection { first, second }, false);
+        valid = false;
+    }
+
+    if valid { Some(result) } else { None }
+}
+
+fn validate_polygon(
+    value: &Value,
+    polygon: usize,
+    issues: &mut Vec<GeometryIssue>,
+) -> Option<Polygon> {
+    let Some(rings) = value.as_array().filter(|rings| !rings.is_empty()) else {
+        issues.push(GeometryIssue {
+            polygon,
+            ring: 0,
+            kind: IssueKind::MalformedRing,
+            repaired: false,
+        });
+        return None;
+    };
+
+    let mut valid = true;
+    let mut result: Vec<Ring> = Vec::new();
+
+    for (ring, value) in rings.iter().enumerate() {
+        match validate_ring(value, polygon, ring, issues) {
+            Some(ring) => result.push(ring),
+            None => valid = false,
+        }
+    }
+
+    if valid { Some(Polygon(result)) } else { None }
+}
+
+/// Validate the geometry extracted from the GeoJSON object and repair what can safely be repaired :
+/// extra dimensions, duplicate consecutive vertices, unclosed rings and winding order.<br>
+/// Every issue found is pushed into ***issues***.<br>
+/// Return the repaired geometry or an Error if it still isn't valid.
+pub fn validate_geometry(
+    geometry: &Value,
+    issues: &mut Vec<GeometryIssue>,
+) -> Result<Geometry, String> {
+    let geometry = geometry
+        .as_object()
+        .ok_or("Inconsistant Value in input. The input need to be a Value::Object().")?;
+
+    let geometry_type = geometry
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+
+    let coordinates = geometry
+        .get("coordinates")
+        .ok_or("Inconsistant geometry : The key 'coordinates' doesn't exists.")?;
+
+    let result: Option<Geometry> = match geometry_type {
+        "Polygon" => validate_polygon(coordinates, 0, issues).map(Geometry::Polygon),
+        "MultiPolygon" => {
+            let polygons = coordinates
+                .as_array()
+                .ok_or("Inconsistant geometry : The key 'coordinates' is not an Array<Value>.")?;
+
+            let polygons = polygons
+                .iter()
+                .enumerate()
+                .map(|(index, polygon)| validate_polygon(polygon, index, issues))
+                .collect::<Vec<Option<Polygon>>>();
+
+            polygons
+                .into_iter()
+                .collect::<Option<Vec<Polygon>>>()
+                .map(|polygons| Geometry::MultiPolygon(MultiPolygon(polygons)))
+        }
+        _ => {
+            issues.push(GeometryIssue {
+                polygon: 0,
+                ring: 0,
+                kind: IssueKind::UnsupportedType {
+                    geometry_type: geometry_type.to_string(),
+                },
+                repaired: false,
+            });
+            None
+        }
+    };
+
+    result.ok_or(format!(
+        "Invalid geometry : {} issue(s) can't be repaired.",
+        issues.iter().filter(|issue| !issue.repaired).count()
+    ))
+}
+
+/// Check the rings of a geometry already validated but modified since (e.g. by the simplification).
+pub fn is_valid(geometry: &Geometry) -> bool {
+    geometry
+        .polygons()
+        .iter()
+        .flat_map(|polygon| &polygon.0)
+        .all(|ring| {
+            ring.len() >= 4
+                && ring.is_closed()
+                && ring.signed_area() != 0f64
+                && find_self_intersection(ring).is_none()
+        })
+}
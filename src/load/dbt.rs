@@ -4,6 +4,8 @@ use std::{
 
 use mylog::error;
 
+use crate::transform::tables::Tables;
+
 const MODEL_FOLDER: &str = "dbt_immo/models";
 
 fn generate_view(files_path: Vec<String>, view_name: &str) -> Result<(), String> {
@@ -25,10 +27,13 @@ fn generate_view(files_path: Vec<String>, view_name: &str) -> Result<(), String>
         .map_err(|e| format!("{}", e))
 }
 
+/// Generate the views of the tables from the Parquet files of the ***folder_path***.<br>
+/// A table exported in Hive partitions (e.g. *'mutations/dep=75/year=2023/part-0.parquet'*)
+/// is read as a single dataset from its folder, instead of its flat files.
 pub fn generate_views(folder_path: &str) -> Result<String, String> {
     let folder_path = PathBuf::from(folder_path);
-    let mut mutations_path: Vec<String> = Vec::new();
-    let mut classes_path: Vec<String> = Vec::new();
+    // The files of each table of Tables::NAMES
+    let mut tables_path: Vec<Vec<String>> = vec![Vec::new(); Tables::NAMES.len()];
 
     let entries = fs::read_dir(&folder_path)
         .map_err(|e| {
@@ -39,22 +44,38 @@ pub fn generate_views(folder_path: &str) -> Result<String, String> {
         .flatten()
         .collect::<Vec<DirEntry>>();
 
+    let mut partitioned_tables: Vec<String> = Vec::new();
+
     for entry in entries {
         let path = entry.path();
 
-        if path.is_file() && path.extension().unwrap_or_default().to_os_string() == "parquet" {
+        if path.is_dir() {
+            let folder_name = path.file_name().unwrap().to_string_lossy();
+
+            if Tables::NAMES.contains(&folder_name.as_ref()) {
+                partitioned_tables.push(folder_name.into());
+            }
+        } else if path.is_file() && path.extension().unwrap_or_default().to_os_string() == "parquet" {
             let filename = path.file_name().unwrap().to_string_lossy();
 
-            if filename.starts_with("mutations") {
-                mutations_path.push(filename.into());
-            } else if filename.starts_with("classes") {
-                classes_path.push(filename.into())
+            if let Some(index) = Tables::NAMES.iter().position(|table| filename.starts_with(table)) {
+                tables_path[index].push(filename.into());
             }
         }
     }
 
-    generate_view(mutations_path, "mutations.sql")?;
-    generate_view(classes_path, "classes.sql")?;
+    // The folder of the partitions replaces the flat files of a previous export
+    let source = |table: &str, files_path: Vec<String>| {
+        if partitioned_tables.iter().any(|folder| folder == table) {
+            vec![table.to_string()]
+        } else {
+            files_path
+        }
+    };
+
+    for (table, files_path) in Tables::NAMES.into_iter().zip(tables_path) {
+        generate_view(source(table, files_path), &format!("{}.sql", table))?;
+    }
 
     Ok("Successfully generate the dbt models !".to_string())
 }
@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use futures::future::join_all;
+use serde_json::{Value, json};
+use tokio::runtime::Builder;
+use tokio::sync::Semaphore;
+use tokio::time::{Duration, sleep};
+
+use crate::extract::api_dvf::{FeatureRows, new_cpu_pool, run_blocking, write_rows};
+use crate::transform::api_dvf::{TransformMode, transform_api_data};
+use crate::transform::parquet_data::ParquetData;
+use crate::transform::partition::OutputLayout;
+use crate::transform::tables::{
+    Adresses, Classes, Dispositions, FeatureSource, Mutation, Parcelles, PrixM2, Tables,
+};
+
+/// Number of API responses transformed by each run of the benchmark.
+const RESPONSES: usize = 2_000;
+/// Number of mutations in each API response.
+const MUTATIONS_PER_RESPONSE: usize = 200;
+/// Number of concurrent fetchers, as the semaphore of ***process_features***.
+const FETCHERS: usize = 100;
+/// Simulated latency of the API requests.
+const LATENCY: Duration = Duration::from_millis(50);
+/// Worker threads of the runtime of the benchmark.
+const RUNTIME_WORKERS: usize = 4;
+
+/// Build an API response of ***MUTATIONS_PER_RESPONSE*** mutations, shaped as the ones of ***mutation/search***.
+fn sample_response() -> Vec<u8> {
+    let features = (0..MUTATIONS_PER_RESPONSE)
+        .map(|index| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[2.35, 48.85], [2.36, 48.85], [2.36, 48.86], [2.35, 48.86], [2.35, 48.85]]]
+                },
+                "properties": {
+                    "idmutation": index,
+                    "vefa": false,
+                    "datemut": "2023-05-17",
+                    "typologie": { "libelle": "Appartement" },
+                    "nature_mutation": { "libelle": "Vente" },
+                    "codtypbien": "121",
+                    "libtypbien": "UN APPARTEMENT",
+                    "sbati": "54.20",
+                    "sterr": "0",
+                    "nblocmut": 2,
+                    "nbpar": 1,
+                    "coddep": "75",
+                    "l_codinsee": ["75102"],
+                    "dispositions": [{
+                        "idmutation": index,
+                        "iddispo": index,
+                        "nodispo": 1,
+                        "valeurfonc": 512000.5,
+                        "parcelles": [{
+                            "idpar": "75102000AB0012",
+                            "parcvendue": true,
+                            "nosect": "AB",
+                            "noplan": "0012",
+                            "adresses": [{
+                                "novoie": "12",
+                                "btq": "B",
+                                "typvoie": "R",
+                                "voie": "de la Paix",
+                                "codepostal": "75002",
+                                "commune": "Paris"
+                            }],
+                            "dcnt": [
+                                { "surface": "54.20", "libregroupement": "Sols" },
+                                { "surface": "8", "libregroupement": "Dépendance" }
+                            ]
+                        }]
+                    }]
+                }
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    serde_json::to_vec(&json!({ "type": "FeatureCollection", "features": features }))
+        .unwrap_or_default()
+}
+
+/// Rows of each table of [Tables::NAMES] extracted from the sample response.
+const EXPECTED_ROWS: [usize; 6] = [
+    MUTATIONS_PER_RESPONSE,
+    MUTATIONS_PER_RESPONSE,
+    MUTATIONS_PER_RESPONSE,
+    2 * MUTATIONS_PER_RESPONSE,
+    MUTATIONS_PER_RESPONSE,
+    MUTATIONS_PER_RESPONSE,
+];
+
+/// Compare the ***counts*** of rows of each table of [Tables::NAMES] with the ***EXPECTED_ROWS***.
+fn check_counts(counts: [usize; 6]) -> Result<(), String> {
+    for ((table, count), expected) in Tables::NAMES.iter().zip(counts).zip(EXPECTED_ROWS) {
+        if count != expected {
+            return Err(format!(
+                "Expected {} rows in {}, found {}",
+                expected, table, count
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check every row of the sample response was extracted, a regression quarantining the features
+/// would otherwise look like a speedup.
+fn check_rows(rows: &FeatureRows) -> Result<(), String> {
+    if !rows.quarantine.is_empty() {
+        return Err(format!(
+            "Expected 0 rows in quarantine, found {}",
+            rows.quarantine.len()
+        ));
+    }
+    check_counts(rows.tables.sizes())
+}
+
+/// Count the rows read back from a Parquet file.
+type RowCounter = fn(&Path) -> Result<usize, String>;
+
+/// Return the number of rows read back from the Parquet file ***path***.
+fn count_rows<T: ParquetData>(path: &Path) -> Result<usize, String> {
+    T::read_from_parquet(path).try_fold(0, |count, row| {
+        row.map(|_| count + 1)
+            .map_err(|e| format!("Failed to read {} : {}", path.display(), e))
+    })
+}
+
+/// Check the Parquet files written for the feature ***feature_id*** in the ***folder*** are read back
+/// with every row of the sample response.
+fn check_files(folder: &Path, feature_id: &str) -> Result<(), String> {
+    // In the order of Tables::NAMES
+    let readers: [RowCounter; 6] = [
+        count_rows::<Mutation>,
+        count_rows::<Dispositions>,
+        count_rows::<Parcelles>,
+        count_rows::<Classes>,
+        count_rows::<Adresses>,
+        count_rows::<PrixM2>,
+    ];
+    let mut counts = [0; 6];
+
+    for ((table, reader), count) in Tables::NAMES.iter().zip(readers).zip(&mut counts) {
+        let files = OutputLayout::from_args()
+            .feature_files(folder, table, feature_id)
+            .map_err(|e| {
+                format!(
+                    "{} - Failed to list the {} files : {}",
+                    feature_id, table, e
+                )
+            })?;
+
+        for path in files {
+            *count += reader(&path).map_err(|e| format!("{} - {}", feature_id, e))?;
+        }
+    }
+
+    check_counts(counts).map_err(|e| format!("{} - {}", feature_id, e))
+}
+
+/// Return the id of the ***response*** of the fetcher ***fetcher_id***, the name of its Parquet files.
+fn feature_id(fetcher_id: usize, response: usize) -> String {
+    format!("bench{}_{}", fetcher_id, response)
+}
+
+/// Parse the ***content*** and write its Parquet files in the ***folder***, as a feature of the pipeline.
+fn transform_and_write(content: &[u8], folder: &Path, feature_id: &str) -> Result<(), String> {
+    let mut rows = FeatureRows::default();
+
+    transform_api_data(
+        content,
+        &FeatureSource::default(),
+        TransformMode::Lenient,
+        &mut rows.tables,
+        &mut rows.quarantine,
+        &mut rows.rejected_classes,
+    )
+    .map_err(|_| format!("{} - Failed to transform the response", feature_id))?;
+
+    check_rows(&rows).map_err(|e| format!("{} - {}", feature_id, e))?;
+
+    write_rows(folder, folder, feature_id, &rows)
+        .map_err(|_| format!("{} - Failed to write the Parquet files", feature_id))
+}
+
+/// Fetch ***responses*** simulated responses, transformed and written in the async task
+/// or handed to the ***cpu_pool*** when there's one.
+async fn fetcher(
+    fetcher_id: usize,
+    content: Arc<Vec<u8>>,
+    folder: Arc<PathBuf>,
+    responses: usize,
+    cpu_pool: Option<Arc<Semaphore>>,
+) -> Result<(), String> {
+    for response in 0..responses {
+        sleep(LATENCY).await;
+
+        let transform = {
+            let content = content.clone();
+            let folder = folder.clone();
+            let feature_id = feature_id(fetcher_id, response);
+
+            move || transform_and_write(&content, &folder, &feature_id)
+        };
+
+        match &cpu_pool {
+            Some(cpu_pool) => run_blocking(cpu_pool, transform)
+                .await
+                .map_err(|_| "The CPU worker failed".to_string())??,
+            None => transform()?,
+        }
+    }
+    Ok(())
+}
+
+/// Return the time taken to transform & write ***RESPONSES*** responses with ***FETCHERS*** fetchers,
+/// the Parquet files are written in a temporary ***folder*** removed afterwards.
+fn run(content: Arc<Vec<u8>>, folder: PathBuf, with_cpu_pool: bool) -> Result<Duration, String> {
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(RUNTIME_WORKERS)
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to build the runtime : {}", e))?;
+
+    fs::create_dir_all(&folder)
+        .map_err(|e| format!("Failed to create the folder {:?} : {}", folder, e))?;
+    let folder = Arc::new(folder);
+
+    // The runtime is driven from a dedicated thread, the pipeline already runs in one.
+    let result = {
+        let folder = folder.clone();
+
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let cpu_pool = with_cpu_pool.then(new_cpu_pool);
+                let start = Instant::now();
+
+                let tasks = (0..FETCHERS)
+                    .map(|fetcher_id| {
+                        tokio::spawn(fetcher(
+                            fetcher_id,
+                            content.clone(),
+                            folder.clone(),
+                            RESPONSES / FETCHERS,
+                            cpu_pool.clone(),
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+
+                for result in join_all(tasks).await {
+                    result.map_err(|e| format!("A fetcher panicked : {}", e))??;
+                }
+
+                Ok(start.elapsed())
+            })
+        })
+        .join()
+        .map_err(|_| "The benchmark panicked".to_string())?
+    };
+
+    // The files are read back once the time is taken
+    let result = result.and_then(|elapsed| {
+        for fetcher_id in 0..FETCHERS {
+            for response in 0..RESPONSES / FETCHERS {
+                check_files(&folder, &feature_id(fetcher_id, response))?;
+            }
+        }
+        Ok(elapsed)
+    });
+
+    let _ = fs::remove_dir_all(folder.as_path());
+    result
+}
+
+/// Compare the throughput of the transformation of the API responses,
+/// inline in the fetchers or on the CPU workers.
+pub fn main() -> Result<String, String> {
+    let content = Arc::new(sample_response());
+
+    let folder = std::env::temp_dir().join(format!("data-immo-bench-{}", std::process::id()));
+
+    let inline = run(content.clone(), folder.join("inline"), false)?;
+    let blocking = run(content, folder.join("cpu_workers"), true)?;
+    let _ = fs::remove_dir_all(&folder);
+
+    let throughput = |elapsed: Duration| RESPONSES as f64 / elapsed.as_secs_f64();
+
+    Ok(format!(
+        "{} responses of {} mutations, {} fetchers, {} runtime workers :\n\
+        - inline      : {:.2?} ({:.1} responses/s)\n\
+        - CPU workers : {:.2?} ({:.1} responses/s)\n\
+        - speedup     : x{:.2}",
+        RESPONSES,
+        MUTATIONS_PER_RESPONSE,
+        FETCHERS,
+        RUNTIME_WORKERS,
+        inline,
+        throughput(inline),
+        blocking,
+        throughput(blocking),
+        inline.as_secs_f64() / blocking.as_secs_f64()
+    ))
+}
@@ -1,6 +1,15 @@
+use crate::pipeline::bench;
 use crate::pipeline::task::{task1, task2, task3};
 
 pub async fn main() {
+    if std::env::args().any(|arg| arg == "--bench") {
+        match bench::main() {
+            Ok(message) => println!("{}", message),
+            Err(message) => eprintln!("{}\n\nFailed to run the benchmark.", message),
+        }
+        return;
+    }
+
     match task1().await {
         Ok(message) => println!("{}\n\nSuccessfully finished the Task1 !\n\n", message),
         Err(message) => {
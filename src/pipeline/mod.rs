@@ -1,2 +1,3 @@
+mod bench;
 pub mod core;
 mod task;
@@ -0,0 +1,440 @@
+use super::tables::Adresse;
+
+/// Reference table of the street types : (***abbreviation***, ***label***).<br>
+/// The abbreviations are the natures of the ways of the FANTOIR file (DGFiP), the labels are the ones stored.
+const STREET_TYPES: &[(&str, &str)] = &[
+    ("ABE", "ABBAYE"),
+    ("ACH", "ANCIEN CHEMIN"),
+    ("AGL", "AGGLOMERATION"),
+    ("AIRE", "AIRE"),
+    ("ALL", "ALLEE"),
+    ("ANGL", "ANGLE"),
+    ("ANSE", "ANSE"),
+    ("ARC", "ARCADE"),
+    ("ART", "ANCIENNE ROUTE"),
+    ("AUT", "AUTOROUTE"),
+    ("AV", "AVENUE"),
+    ("BAST", "BASTION"),
+    ("BCH", "BAS CHEMIN"),
+    ("BCLE", "BOUCLE"),
+    ("BD", "BOULEVARD"),
+    ("BEGI", "BEGUINAGE"),
+    ("BER", "BERGE"),
+    ("BOIS", "BOIS"),
+    ("BRE", "BARRIERE"),
+    ("BRG", "BOURG"),
+    ("BSTD", "BASTIDE"),
+    ("BUT", "BUTTE"),
+    ("CALE", "CALE"),
+    ("CAMP", "CAMP"),
+    ("CAR", "CARREFOUR"),
+    ("CARE", "CARRIERE"),
+    ("CARR", "CARRE"),
+    ("CAU", "CARREAU"),
+    ("CAV", "CAVEE"),
+    ("CGNE", "CAMPAGNE"),
+    ("CHE", "CHEMIN"),
+    ("CHEM", "CHEMINEMENT"),
+    ("CHEZ", "CHEZ"),
+    ("CHI", "CHARMILLE"),
+    ("CHL", "CHALET"),
+    ("CHP", "CHAPELLE"),
+    ("CHS", "CHAUSSEE"),
+    ("CHT", "CHATEAU"),
+    ("CHV", "CHEMIN VICINAL"),
+    ("CITE", "CITE"),
+    ("CLOI", "CLOITRE"),
+    ("CLOS", "CLOS"),
+    ("COL", "COL"),
+    ("COLI", "COLLINE"),
+    ("COR", "CORNICHE"),
+    ("COTE", "COTE"),
+    ("COTT", "COTTAGE"),
+    ("COUR", "COUR"),
+    ("CPG", "CAMPING"),
+    ("CRS", "COURS"),
+    ("CST", "CASTEL"),
+    ("CTR", "CONTOUR"),
+    ("CTRE", "CENTRE"),
+    ("DARS", "DARSE"),
+    ("DEG", "DEGRE"),
+    ("DIG", "DIGUE"),
+    ("DOM", "DOMAINE"),
+    ("DSC", "DESCENTE"),
+    ("ECL", "ECLUSE"),
+    ("EGL", "EGLISE"),
+    ("EN", "ENCEINTE"),
+    ("ENC", "ENCLOS"),
+    ("ENV", "ENVIRONS"),
+    ("ESC", "ESCALIER"),
+    ("ESP", "ESPLANADE"),
+    ("ESPA", "ESPACE"),
+    ("ETNG", "ETANG"),
+    ("FG", "FAUBOURG"),
+    ("FON", "FONTAINE"),
+    ("FORM", "FORUM"),
+    ("FORT", "FORT"),
+    ("FOS", "FOSSE"),
+    ("FOYR", "FOYER"),
+    ("FRM", "FERME"),
+    ("GAL", "GALERIE"),
+    ("GARE", "GARE"),
+    ("GARN", "GARENNE"),
+    ("GBD", "GRAND BOULEVARD"),
+    ("GDEN", "GRAND ENSEMBLE"),
+    ("GPE", "GROUPE"),
+    ("GPT", "GROUPEMENT"),
+    ("GR", "GRANDE RUE"),
+    ("GRI", "GRILLE"),
+    ("GRIM", "GRIMPETTE"),
+    ("HAM", "HAMEAU"),
+    ("HCH", "HAUT CHEMIN"),
+    ("HIP", "HIPPODROME"),
+    ("HLE", "HALLE"),
+    ("HLM", "HLM"),
+    ("ILE", "ILE"),
+    ("IMM", "IMMEUBLE"),
+    ("IMP", "IMPASSE"),
+    ("JARD", "JARDIN"),
+    ("JTE", "JETEE"),
+    ("LD", "LIEU-DIT"),
+    ("LEVE", "LEVEE"),
+    ("LOT", "LOTISSEMENT"),
+    ("MAIL", "MAIL"),
+    ("MAN", "MANOIR"),
+    ("MAR", "MARCHE"),
+    ("MAS", "MAS"),
+    ("MET", "METRO"),
+    ("MF", "MAISON FORESTIERE"),
+    ("MLN", "MOULIN"),
+    ("MTE", "MONTEE"),
+    ("MUS", "MUSEE"),
+    ("NTE", "NOUVELLE ROUTE"),
+    ("PAE", "PETITE AVENUE"),
+    ("PAL", "PALAIS"),
+    ("PARC", "PARC"),
+    ("PAS", "PASSAGE"),
+    ("PASS", "PASSE"),
+    ("PAT", "PATIO"),
+    ("PAV", "PAVILLON"),
+    ("PCH", "PETIT CHEMIN"),
+    ("PERI", "PERIPHERIQUE"),
+    ("PIM", "PETITE IMPASSE"),
+    ("PKG", "PARKING"),
+    ("PL", "PLACE"),
+    ("PLAG", "PLAGE"),
+    ("PLAN", "PLAN"),
+    ("PLCI", "PLACIS"),
+    ("PLE", "PASSERELLE"),
+    ("PLN", "PLAINE"),
+    ("PLT", "PLATEAU"),
+    ("PN", "PASSAGE A NIVEAU"),
+    ("PNT", "POINTE"),
+    ("PONT", "PONT"),
+    ("PORQ", "PORTIQUE"),
+    ("PORT", "PORT"),
+    ("POT", "POTERNE"),
+    ("POUR", "POURTOUR"),
+    ("PRE", "PRE"),
+    ("PROM", "PROMENADE"),
+    ("PRQ", "PRESQU'ILE"),
+    ("PRV", "PARVIS"),
+    ("PSTY", "PERISTYLE"),
+    ("PTA", "PETITE ALLEE"),
+    ("PTE", "PORTE"),
+    ("PTR", "PETITE RUE"),
+    ("QU", "QUAI"),
+    ("QUA", "QUARTIER"),
+    ("R", "RUE"),
+    ("RAC", "RACCOURCI"),
+    ("RAID", "RAIDILLON"),
+    ("REM", "REMPART"),
+    ("RES", "RESIDENCE"),
+    ("RLE", "RUELLE"),
+    ("ROC", "ROCADE"),
+    ("ROQT", "ROQUET"),
+    ("RPE", "RAMPE"),
+    ("RPT", "ROND-POINT"),
+    ("RTD", "ROTONDE"),
+    ("RTE", "ROUTE"),
+    ("SEN", "SENTIER"),
+    ("SQ", "SQUARE"),
+    ("STA", "STATION"),
+    ("STDE", "STADE"),
+    ("TOUR", "TOUR"),
+    ("TPL", "TERRE-PLEIN"),
+    ("TRA", "TRAVERSE"),
+    ("TRN", "TERRAIN"),
+    ("TRT", "TERTRE"),
+    ("TSSE", "TERRASSE"),
+    ("TUN", "TUNNEL"),
+    ("VAL", "VAL"),
+    ("VC", "VOIE COMMUNALE"),
+    ("VCHE", "VIEUX CHEMIN"),
+    ("VEN", "VENELLE"),
+    ("VGE", "VILLAGE"),
+    ("VIA", "VIA"),
+    ("VLA", "VILLA"),
+    ("VOIE", "VOIE"),
+    ("VOIR", "VOIRIE"),
+    ("VOUT", "VOUTE"),
+    ("VOY", "VOYEUL"),
+    ("VTE", "VIEILLE ROUTE"),
+    ("ZA", "ZONE ARTISANALE"),
+    ("ZAC", "ZONE D'AMENAGEMENT CONCERTE"),
+    ("ZAD", "ZONE D'AMENAGEMENT DIFFERE"),
+    ("ZI", "ZONE INDUSTRIELLE"),
+    ("ZONE", "ZONE"),
+    ("ZUP", "ZONE A URBANISER EN PRIORITE"),
+];
+
+/// Trim the ***value***, collapse its whitespaces and put it in uppercase.<br>
+/// Return ***None*** if nothing is left.
+fn clean(value: &Option<String>) -> Option<String> {
+    let value = value
+        .as_deref()?
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase();
+
+    if value.is_empty() { None } else { Some(value) }
+}
+
+/// Return the label of the street type, from its abbreviation or its label.
+fn normalize_street_type(typvoie: &str) -> Option<&'static str> {
+    let typvoie = typvoie.trim_end_matches('.');
+
+    STREET_TYPES
+        .iter()
+        .find(|(abbreviation, label)| *abbreviation == typvoie || *label == typvoie)
+        .map(|(_, label)| *label)
+}
+
+/// Return the department code of the ***code_insee*** (e.g. ***2A*** for *'2A004'*, ***974*** for *'97411'*).
+pub fn code_departement(code_insee: &str) -> Option<String> {
+    if code_insee.starts_with("97") || code_insee.starts_with("98") {
+        code_insee.get(..3).map(String::from)
+    } else {
+        code_insee.get(..2).map(String::from)
+    }
+}
+
+/// Return the prefix of the postal codes of the department (***20*** for the Corsican ***2A*** & ***2B***).
+fn postal_prefix(code_dep: &str) -> &str {
+    match code_dep {
+        "2A" | "2B" => "20",
+        code_dep => code_dep,
+    }
+}
+
+/// Pad the postal code lost its leading zero (e.g. *'1000'* extracted from a JSON number)
+/// and check it belongs to the department.
+fn normalize_postal_code(codepostal: &str, code_dep: Option<&str>) -> Result<String, String> {
+    let codepostal = format!("{:0>5}", codepostal.replace(' ', ""));
+
+    if codepostal.len() != 5 || !codepostal.chars().all(|c| c.is_ascii_digit()) {
+        return Err(codepostal);
+    }
+
+    match code_dep {
+        Some(code_dep) if !codepostal.starts_with(postal_prefix(code_dep)) => Err(codepostal),
+        _ => Ok(codepostal),
+    }
+}
+
+/// Normalize the ***adresse*** : casing & whitespaces, street type and postal code.<br>
+/// The postal code is checked against the department ***code_dep*** of the parcel.<br>
+/// Return ***false*** if a street type or a postal code can't be normalized, the value is then kept cleaned.
+pub fn normalize_adresse(adresse: &mut Adresse, code_dep: Option<&str>) -> bool {
+    let mut normalized = true;
+
+    adresse.btq = clean(&adresse.btq);
+    adresse.voie = clean(&adresse.voie);
+    adresse.codvoie = clean(&adresse.codvoie);
+    adresse.commune = clean(&adresse.commune);
+    adresse.novoie = clean(&adresse.novoie).map(|novoie| {
+        let trimmed = novoie.trim_start_matches('0');
+        if trimmed.is_empty() {
+            novoie
+        } else {
+            trimmed.to_string()
+        }
+    });
+
+    adresse.typvoie =
+        clean(&adresse.typvoie).map(|typvoie| match normalize_street_type(&typvoie) {
+            Some(label) => label.to_string(),
+            None => {
+                normalized = false;
+                typvoie
+            }
+        });
+
+    adresse.codepostal = clean(&adresse.codepostal).map(|codepostal| {
+        match normalize_postal_code(&codepostal, code_dep) {
+            Ok(codepostal) => codepostal,
+            Err(codepostal) => {
+                normalized = false;
+                codepostal
+            }
+        }
+    });
+
+    normalized
+}
+
+/// Format the ***adresse*** on one line (e.g. *'12 B RUE DE LA PAIX, 75002 PARIS'*).
+pub fn format_adresse(adresse: &Adresse) -> Option<String> {
+    let street = [
+        &adresse.novoie,
+        &adresse.btq,
+        &adresse.typvoie,
+        &adresse.voie,
+    ]
+    .into_iter()
+    .flatten()
+    .map(String::as_str)
+    .collect::<Vec<&str>>()
+    .join(" ");
+
+    let city = [&adresse.codepostal, &adresse.commune]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    match (street.is_empty(), city.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(street),
+        (true, false) => Some(city),
+        (false, false) => Some(format!("{}, {}", street, city)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adresse(values: [Option<&str>; 7]) -> Adresse {
+        let [btq, voie, novoie, codvoie, commune, typvoie, codepostal] =
+            values.map(|value| value.map(String::from));
+
+        Adresse {
+            btq,
+            voie,
+            novoie,
+            codvoie,
+            commune,
+            typvoie,
+            codepostal,
+        }
+    }
+
+    #[test]
+    fn street_types() {
+        for (typvoie, label) in [
+            ("R", "RUE"),
+            ("RUE", "RUE"),
+            ("AV.", "AVENUE"),
+            ("HAM", "HAMEAU"),
+            ("CHEM", "CHEMINEMENT"),
+            ("SQ", "SQUARE"),
+            ("LOT", "LOTISSEMENT"),
+            ("RES", "RESIDENCE"),
+            ("QUA", "QUARTIER"),
+            ("ZAC", "ZONE D'AMENAGEMENT CONCERTE"),
+        ] {
+            assert_eq!(normalize_street_type(typvoie), Some(label), "{}", typvoie);
+        }
+        assert_eq!(normalize_street_type("XYZ"), None);
+    }
+
+    #[test]
+    fn postal_codes() {
+        assert_eq!(
+            normalize_postal_code("75002", Some("75")),
+            Ok("75002".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("1000", Some("01")),
+            Ok("01000".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("75 002", None),
+            Ok("75002".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("97411", Some("974")),
+            Ok("97411".to_string())
+        );
+
+        // The Corsican departments share the postal codes starting with 20
+        assert_eq!(
+            normalize_postal_code("20000", Some("2A")),
+            Ok("20000".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("20200", Some("2B")),
+            Ok("20200".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("2A000", Some("2A")),
+            Err("2A000".to_string())
+        );
+
+        assert_eq!(
+            normalize_postal_code("75002", Some("69")),
+            Err("75002".to_string())
+        );
+        assert_eq!(
+            normalize_postal_code("750021", None),
+            Err("750021".to_string())
+        );
+    }
+
+    #[test]
+    fn normalized_adresse() {
+        let mut value = adresse([
+            Some(" b "),
+            Some("de la   paix"),
+            Some("0012"),
+            None,
+            Some("Paris"),
+            Some("r"),
+            Some("75002"),
+        ]);
+
+        assert!(normalize_adresse(&mut value, Some("75")));
+        assert_eq!(
+            format_adresse(&value),
+            Some("12 B RUE DE LA PAIX, 75002 PARIS".to_string())
+        );
+
+        let mut value = adresse([None, None, None, None, None, Some("XYZ"), Some("69001")]);
+        assert!(!normalize_adresse(&mut value, Some("75")));
+        assert_eq!(value.typvoie.as_deref(), Some("XYZ"));
+    }
+
+    #[test]
+    fn formatted_adresse() {
+        let street = adresse([
+            None,
+            Some("DE LA PAIX"),
+            Some("12"),
+            None,
+            None,
+            Some("RUE"),
+            None,
+        ]);
+        let city = adresse([None, None, None, None, Some("PARIS"), None, Some("75002")]);
+
+        assert_eq!(
+            format_adresse(&street),
+            Some("12 RUE DE LA PAIX".to_string())
+        );
+        assert_eq!(format_adresse(&city), Some("75002 PARIS".to_string()));
+        assert_eq!(format_adresse(&Adresse::default()), None);
+    }
+}
@@ -1,228 +1,296 @@
+use std::collections::HashSet;
+
 use mylog::error;
-use serde_json::{self, Map, Value};
+use rust_decimal::Decimal;
+
+use super::api_model::{
+    Disposition, Feature, FeatureCollection, RawFeatureCollection, RecordError, parse_feature,
+    parse_response,
+};
+use super::price::compute_prix_m2;
+use super::quarantine::QuarantineRecord;
+use super::tables::{
+    Adresses, ClassFilter, Classes, Dispositions, FeatureSource, Mutation, Parcelles,
+    RejectedClass, Tables,
+};
+
+/// Keep the full geometry of the mutations as WKB, the centroid is always kept.
+const STORE_GEOMETRY_WKB: bool = false;
+
+/// Rules to drop the classes of the parcels, the dropped classes are counted in the run report.
+pub const CLASS_FILTER: ClassFilter = ClassFilter {
+    min_surface: Decimal::ONE,
+    drop_missing_libelle: true,
+    quarantine: false,
+};
 
-use super::tables::{Classes, Mutation, SharedMutationProps};
-use crate::extract::utils::IdGenerator;
+/// Behaviour of the transformation when a record of the API response is malformed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformMode {
+    /// The whole response is rejected.
+    Strict,
+    /// The record is skipped and put in quarantine.
+    Lenient,
+}
+
+/// Prefix the path of the classes rejected since ***size*** with the location of the parent value.
+fn prefix_rejected(rejected_classes: &mut [RejectedClass], size: usize, parent: &str) {
+    for class in &mut rejected_classes[size..] {
+        class.path = format!("{}.{}", parent, class.path);
+    }
+}
 
 fn map_parcelles(
-    parcelles: Vec<Map<String, Value>>,
-    shared_props: &SharedMutationProps,
-    valeurfonc: f64,
-    idmutation: u64,
-    id_generator: &IdGenerator,
-    mutations: &mut Vec<Mutation>,
-    classes: &mut Vec<Classes>,
-) -> Result<(), ()> {
-    for parcelle in parcelles {
-        let dcnt = parcelle
-            .get("dcnt")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'dcnt'"))?
-            .as_array()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Array<Value>"))?;
-
-        let id = id_generator.next_id();
-
-        let classes_rows = Classes::extract(dcnt, id)?;
-        let mutation_row = Mutation::extract(parcelle, shared_props, valeurfonc, idmutation, id)?;
-
-        classes.extend(classes_rows);
-        mutations.push(mutation_row);
+    disposition: &Disposition,
+    idd: u64,
+    tables: &mut Tables,
+    rejected_classes: &mut Vec<RejectedClass>,
+) -> Result<(), RecordError> {
+    for (index, parcelle) in disposition.parcelles.iter().enumerate() {
+        let parcelle_row = Parcelles::extract(parcelle, idd);
+        let idg = parcelle_row.idg;
+        let size = rejected_classes.len();
+
+        tables.parcelles.push(parcelle_row);
+        tables.classes.extend(
+            Classes::extract(&parcelle.dcnt, idg, &CLASS_FILTER, rejected_classes)
+                .map_err(|e| e.prefix(&format!("parcelles[{}]", index)))?,
+        );
+        tables
+            .adresses
+            .extend(Adresses::extract(&parcelle.adresses, &parcelle.idpar, idg));
+
+        prefix_rejected(rejected_classes, size, &format!("parcelles[{}]", index));
     }
     Ok(())
 }
 
 fn map_dispositions(
-    dispositions: &[Value],
-    shared_props: &SharedMutationProps,
-    id_generator: &IdGenerator,
-    mutations: &mut Vec<Mutation>,
-    classes: &mut Vec<Classes>,
+    dispositions: &[Disposition],
+    idm: u64,
+    tables: &mut Tables,
+    rejected_classes: &mut Vec<RejectedClass>,
+) -> Result<(), RecordError> {
+    for (index, disposition) in dispositions.iter().enumerate() {
+        let prefix = |e: RecordError| e.prefix(&format!("dispositions[{}]", index));
+
+        let disposition_row = Dispositions::extract(disposition, idm, index).map_err(prefix)?;
+        let idd = disposition_row.idd;
+        let size = rejected_classes.len();
+
+        tables.dispositions.push(disposition_row);
+        map_parcelles(disposition, idd, tables, rejected_classes).map_err(prefix)?;
+
+        prefix_rejected(rejected_classes, size, &format!("dispositions[{}]", index));
+    }
+    Ok(())
+}
+
+fn map_feature(
+    feature: &Feature,
+    source: &FeatureSource,
+    tables: &mut Tables,
+    rejected_classes: &mut Vec<RejectedClass>,
+) -> Result<(), RecordError> {
+    let mutation = Mutation::extract(feature, source, STORE_GEOMETRY_WKB)
+        .map_err(|e| e.prefix("properties"))?;
+    let idm = mutation.idm;
+    let sbati = mutation.sbati;
+
+    let sizes = tables.sizes();
+    let size = rejected_classes.len();
+
+    tables.mutations.push(mutation);
+    map_dispositions(
+        &feature.properties.dispositions,
+        idm,
+        tables,
+        rejected_classes,
+    )
+    .map_err(|e| e.prefix("properties"))?;
+    prefix_rejected(rejected_classes, size, "properties");
+
+    let prix_m2 = compute_prix_m2(
+        idm,
+        sbati,
+        &tables.dispositions[sizes[1]..],
+        &tables.parcelles[sizes[2]..],
+        &tables.classes[sizes[3]..],
+    );
+    prix_m2.check()?;
+    tables.prix_m2.push(prix_m2);
+
+    Ok(())
+}
+
+/// Transform the API response into the rows of the ***tables***.<br>
+/// In [TransformMode::Strict] the first malformed feature rejects the whole response,
+/// in [TransformMode::Lenient] it's skipped and pushed into ***quarantine***.<br>
+/// The classes dropped by the [CLASS_FILTER] are pushed into ***rejected_classes***.
+pub fn transform_api_data(
+    data: &[u8],
+    source: &FeatureSource,
+    mode: TransformMode,
+    tables: &mut Tables,
+    quarantine: &mut Vec<QuarantineRecord>,
+    rejected_classes: &mut Vec<RejectedClass>,
 ) -> Result<(), ()> {
-    let dispositions = dispositions
-        .iter()
-        .flat_map(|v| {
-            if let Value::Object(map) = v {
-                Ok(map)
-            } else {
-                Err(())
+    let sizes = tables.sizes();
+    let rejected_size = rejected_classes.len();
+
+    match mode {
+        TransformMode::Strict => {
+            let collection: FeatureCollection = parse_response(data)
+                .map_err(|e| error!("Failed to parse the API response at {}", e))?;
+
+            for (index, feature) in collection.features.iter().enumerate() {
+                let size = rejected_classes.len();
+
+                if let Err(e) = map_feature(feature, source, tables, rejected_classes) {
+                    tables.truncate(sizes);
+                    rejected_classes.truncate(rejected_size);
+                    error!(
+                        "Failed to transform the API response at {}",
+                        e.prefix(&format!("features[{}]", index))
+                    );
+                    return Err(());
+                }
+
+                prefix_rejected(rejected_classes, size, &format!("features[{}]", index));
             }
-        })
-        .collect::<Vec<&Map<String, Value>>>();
-
-    for disposition in dispositions {
-        let parcelles = disposition
-            .get("parcelles")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'parcelles'"))?
-            .as_array()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Array<Value>"))?;
-
-        let parcelles = parcelles
-            .iter()
-            .flat_map(|v| {
-                if let Value::Object(map) = v {
-                    Ok(map.clone())
+        }
+        TransformMode::Lenient => {
+            let collection: RawFeatureCollection = parse_response(data)
+                .map_err(|e| error!("Failed to parse the API response at {}", e))?;
+
+            for (index, value) in collection.features.iter().enumerate() {
+                let sizes = tables.sizes();
+                let size = rejected_classes.len();
+
+                let result = parse_feature(value)
+                    .and_then(|feature| map_feature(&feature, source, tables, rejected_classes));
+
+                if let Err(e) = result {
+                    tables.truncate(sizes);
+                    rejected_classes.truncate(size);
+
+                    let e = e.prefix(&format!("features[{}]", index));
+                    error!("Quarantine the record at {}", e);
+                    quarantine.push(QuarantineRecord::new(e.path, e.reason, value.clone()));
                 } else {
-                    Err(())
+                    prefix_rejected(rejected_classes, size, &format!("features[{}]", index));
                 }
-            })
-            .collect::<Vec<Map<String, Value>>>();
-
-        let valeurfonc = disposition
-            .get("valeurfonc")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'valeurfonc'"))?
-            .as_f64()
-            .ok_or(())
-            .map_err(|_| {
-                error!(
-                    "Inconsistant value : Expected a f64 - value={:?}",
-                    disposition.get("valeurfonc")
-                )
-            })?;
-
-        let idmutation = disposition
-            .get("idmutation")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'idmutation'"))?
-            .as_u64()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a u64"))?;
-
-        map_parcelles(
-            parcelles,
-            shared_props,
-            valeurfonc,
-            idmutation,
-            id_generator,
-            mutations,
-            classes,
-        )?;
+            }
+        }
     }
 
     Ok(())
 }
 
-fn map_properties(
-    properties: &Map<String, Value>,
-    id_generator: &IdGenerator,
-    mutations: &mut Vec<Mutation>,
-    classes: &mut Vec<Classes>,
-) -> Result<(), ()> {
-    let vefa = properties
-        .get("vefa")
-        .ok_or(())
-        .map_err(|_| error!("Failed to get the value of the key 'vefa'"))?
-        .as_bool()
-        .ok_or(())
-        .map_err(|_| error!("Inconsistant value : Expected a Boolean"))?;
-
-    let datemut = String::from(
-        properties
-            .get("datemut")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'datemut'"))?
-            .as_str()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Str"))?,
-    );
+/// Remove the rows already extracted : the mutations returned twice, with their dispositions,
+/// parcels, classes, adresses and price per square metre.<br>
+/// Mutations touching the split line of a geometry are returned by both halves,
+/// their rows get the same ids and only the first ones are kept.<br>
+/// Return the number of mutations removed.
+pub fn remove_duplicates_mutations(tables: &mut Tables) -> usize {
+    let size = tables.mutations.len();
 
-    let typologie = String::from(
-        properties
-            .get("typologie")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'typologie'"))?
-            .as_object()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Map<String, Value>"))?
-            .get("libelle")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'libelle'"))?
-            .as_str()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Str"))?,
-    );
+    let mut keys: HashSet<u64> = HashSet::new();
+    tables
+        .mutations
+        .retain(|mutation| keys.insert(mutation.idm));
 
-    let nature = String::from(
-        properties
-            .get("nature_mutation")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'nature_mutation'"))?
-            .as_object()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Map<String, Value>"))?
-            .get("libelle")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'libelle'"))?
-            .as_str()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Str"))?,
-    );
+    let mut keys: HashSet<u64> = HashSet::new();
+    tables
+        .dispositions
+        .retain(|disposition| keys.insert(disposition.idd));
 
-    let shared_props = SharedMutationProps::new(vefa, typologie, datemut, nature);
+    let mut keys: HashSet<u64> = HashSet::new();
+    tables
+        .parcelles
+        .retain(|parcelle| keys.insert(parcelle.idg));
 
-    let dispositions = properties
-        .get("dispositions")
-        .ok_or(())
-        .map_err(|_| error!("Failed to get the value of the key 'dispositions'"))?
-        .as_array()
-        .ok_or(())
-        .map_err(|_| error!("Inconsistant value : Expected a Array<Value>"))?;
+    let mut keys: HashSet<(u64, u32)> = HashSet::new();
+    tables
+        .classes
+        .retain(|class| keys.insert((class.idg, class.rank)));
 
-    map_dispositions(
-        dispositions,
-        &shared_props,
-        id_generator,
-        mutations,
-        classes,
-    )
+    let mut keys: HashSet<(u64, u32)> = HashSet::new();
+    tables
+        .adresses
+        .retain(|adresse| keys.insert((adresse.idg, adresse.rank)));
+
+    let mut keys: HashSet<u64> = HashSet::new();
+    tables.prix_m2.retain(|prix_m2| keys.insert(prix_m2.idm));
+
+    size - tables.mutations.len()
 }
 
-pub fn transform_api_data(
-    data: String,
-    id_generator: &IdGenerator,
-    mutations: &mut Vec<Mutation>,
-    classes: &mut Vec<Classes>,
-) -> Result<(), ()> {
-    let value: Value = serde_json::from_str(&data).map_err(|e| {
-        error!(
-            "Failed to convert the '{}' content to a Value : {}",
-            data, e
-        )
-    })?;
-
-    let features = value
-        .as_object()
-        .ok_or(())
-        .map_err(|_| error!("Inconsistant value : Expected a Value::Object<Map<String, Value>>"))?
-        .get("features")
-        .ok_or(())
-        .map_err(|_| error!("Failed to get the value of the key 'features'"))?
-        .as_array()
-        .ok_or(())
-        .map_err(|_| error!("Inconsistant value : Expected an Array<Value>"))?;
-
-    for feature in features {
-        let properties = feature
-            .as_object()
-            .ok_or(())
-            .map_err(|_| {
-                error!("Inconsistant value : Expected a Value::Object<Map<String, Value>>")
-            })?
-            .get("properties")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'properties'"))?
-            .as_object()
-            .ok_or(())
-            .map_err(|_| {
-                error!("Inconsistant value : Expected a Value::Object<Map<String, Value>>")
-            })?;
-
-        map_properties(properties, id_generator, mutations, classes)?;
+/// Remove the classes rejected twice with the same ***(idg, rank)***, as [remove_duplicates_mutations]
+/// does for the classes kept, so they're counted & quarantined once.
+pub fn remove_duplicates_rejected(rejected_classes: &mut Vec<RejectedClass>) {
+    let mut keys: HashSet<(u64, u32)> = HashSet::new();
+    rejected_classes.retain(|class| keys.insert((class.idg, class.rank)));
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn feature(idmutation: u64, valeurfonc: &str) -> Value {
+        json!({
+            "type": "Feature",
+            "geometry": null,
+            "properties": {
+                "idmutation": idmutation,
+                "vefa": false,
+                "datemut": "2023-05-17",
+                "typologie": { "libelle": "Appartement" },
+                "nature_mutation": { "libelle": "Vente" },
+                "dispositions": [{
+                    "idmutation": idmutation,
+                    "iddispo": 1,
+                    "valeurfonc": valeurfonc,
+                    "parcelles": [{
+                        "idpar": "75102000AB0012",
+                        "parcvendue": true,
+                        "adresses": [],
+                        "dcnt": [{ "surface": "54.20", "libregroupement": "Sols" }]
+                    }]
+                }]
+            }
+        })
     }
 
-    Ok(())
+    #[test]
+    fn out_of_range_decimals_are_quarantined() {
+        let response = json!({
+            "type": "FeatureCollection",
+            "features": [feature(1, "512000.50"), feature(2, "12345678901234567.00")]
+        });
+
+        let mut tables = Tables::default();
+        let mut quarantine = Vec::new();
+        let mut rejected_classes = Vec::new();
+
+        transform_api_data(
+            &serde_json::to_vec(&response).unwrap(),
+            &FeatureSource::default(),
+            TransformMode::Lenient,
+            &mut tables,
+            &mut quarantine,
+            &mut rejected_classes,
+        )
+        .unwrap();
+
+        assert_eq!(tables.sizes(), [1, 1, 1, 1, 0, 1]);
+        assert_eq!(tables.mutations[0].idmutation, 1);
+        assert_eq!(quarantine.len(), 1);
+        assert_eq!(
+            quarantine[0].path,
+            "features[1].properties.dispositions[0].valeurfonc"
+        );
+    }
 }
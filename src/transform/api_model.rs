@@ -0,0 +1,250 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::extract::geometry::Geometry;
+
+/// Error on a record of the API response, with the path of the value in the record.
+#[derive(Debug, Clone)]
+pub struct RecordError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl RecordError {
+    pub fn new(path: &str, reason: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Prefix the path with the location of the parent value.
+    pub fn prefix(mut self, parent: &str) -> Self {
+        self.path = if self.path.is_empty() {
+            parent.to_string()
+        } else {
+            format!("{}.{}", parent, self.path)
+        };
+        self
+    }
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' : {}", self.path, self.reason)
+    }
+}
+
+/// Response of the endpoint ***mutation/search*** of the API DVF+.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+/// Response of the API with the features left unparsed, to isolate the malformed ones.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawFeatureCollection {
+    pub features: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Feature {
+    pub properties: Properties,
+    #[serde(default, deserialize_with = "lenient_geometry")]
+    pub geometry: Option<Geometry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Properties {
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pub idmutation: Option<u64>,
+    pub vefa: bool,
+    pub datemut: String,
+    pub typologie: Libelle,
+    pub nature_mutation: Libelle,
+    pub dispositions: Vec<Disposition>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub codtypbien: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub libtypbien: Option<String>,
+    #[serde(default, deserialize_with = "lenient_decimal")]
+    pub sbati: Option<Decimal>,
+    #[serde(default, deserialize_with = "lenient_decimal")]
+    pub sterr: Option<Decimal>,
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub nblocmut: Option<u32>,
+    #[serde(default, deserialize_with = "lenient_u32")]
+    pub nbpar: Option<u32>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub coddep: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string_list")]
+    pub l_codinsee: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Libelle {
+    pub libelle: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Disposition {
+    pub idmutation: u64,
+    #[serde(with = "rust_decimal::serde::arbitrary_precision")]
+    pub valeurfonc: Decimal,
+    pub parcelles: Vec<Parcelle>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pub iddispo: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pub nodispo: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Parcelle {
+    pub idpar: String,
+    pub parcvendue: bool,
+    pub adresses: Vec<ApiAdresse>,
+    pub dcnt: Vec<Dcnt>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub nosect: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub noplan: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiAdresse {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub btq: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub voie: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub novoie: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub codvoie: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub commune: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub typvoie: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub codepostal: Option<String>,
+}
+
+/// Surface of a class of premises (*'dcnt'*) of a parcel.<br>
+/// The ***raw*** entry of the API is kept to put the rejected classes in the quarantine as they were received.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "Value")]
+pub struct Dcnt {
+    pub surface: Option<Decimal>,
+    pub libregroupement: Option<String>,
+    pub raw: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct DcntFields {
+    #[serde(default, deserialize_with = "lenient_decimal")]
+    surface: Option<Decimal>,
+    #[serde(default, deserialize_with = "lenient_string")]
+    libregroupement: Option<String>,
+}
+
+impl TryFrom<Value> for Dcnt {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: Value) -> Result<Self, Self::Error> {
+        let fields = DcntFields::deserialize(&raw)?;
+
+        Ok(Self {
+            surface: fields.surface,
+            libregroupement: fields.libregroupement,
+            raw,
+        })
+    }
+}
+
+/// Accept a string, a number or null for the optional text fields of the API.
+fn lenient_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(value) => Some(value),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    })
+}
+
+/// Accept a list of strings or numbers (e.g. the INSEE codes), a single value or null.
+fn lenient_string_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = |value: Value| match value {
+        Value::String(value) => Some(value),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    };
+
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(values) => Some(values.into_iter().filter_map(text).collect()),
+        Value::Null => None,
+        value => text(value).map(|value| vec![value]),
+    })
+}
+
+/// Keep the geometry of the mutation only if it's a Polygon or a MultiPolygon.
+fn lenient_geometry<'de, D>(deserializer: D) -> Result<Option<Geometry>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(serde_json::from_value(Value::deserialize(deserializer)?).ok())
+}
+
+/// Accept a number, a numeric string or null for the optional numeric fields of the API.<br>
+/// The decimals are parsed from the text of the number, without going through a float.
+fn lenient_decimal<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = match Value::deserialize(deserializer)? {
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => value.trim().to_string(),
+        _ => return Ok(None),
+    };
+
+    Ok(Decimal::from_str_exact(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .ok())
+}
+
+fn lenient_u64<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(value) => value.as_u64(),
+        Value::String(value) => value.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn lenient_u32<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(lenient_u64(deserializer)?.and_then(|value| u32::try_from(value).ok()))
+}
+
+/// Parse the response of the API in one pass.<br>
+/// The error contains the path of the value that doesn't match the model (e.g. *features[3].properties.typologie.libelle*).
+pub fn parse_response<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, RecordError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(data);
+
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|e| RecordError::new(&e.path().to_string(), &e.inner().to_string()))
+}
+
+/// Parse one feature of a [RawFeatureCollection].
+pub fn parse_feature(value: &Value) -> Result<Feature, RecordError> {
+    serde_path_to_error::deserialize(value)
+        .map_err(|e| RecordError::new(&e.path().to_string(), &e.inner().to_string()))
+}
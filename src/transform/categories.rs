@@ -0,0 +1,127 @@
+/// Property type of a mutation (*'typologie'* of the API DVF+).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Typologie {
+    Maison,
+    Appartement,
+    Dependance,
+    Activite,
+    BatiMixte,
+    Terrain,
+    /// Libellé unknown when this version was written, kept as it is.
+    Other(String),
+}
+
+/// Nature of a mutation (*'nature_mutation'* of the API DVF+).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NatureMutation {
+    Vente,
+    VenteEnLEtatFuturDAchevement,
+    VenteTerrainABatir,
+    Echange,
+    Adjudication,
+    Expropriation,
+    /// Libellé unknown when this version was written, kept as it is.
+    Other(String),
+}
+
+/// Code stored for the libellés that aren't known.
+pub const OTHER_CODE: u8 = 99;
+
+/// Put the libellé in lowercase without accents and extra whitespaces, to compare it.
+fn normalize(libelle: &str) -> String {
+    libelle
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            '’' => '\'',
+            c => c,
+        })
+        .collect()
+}
+
+impl Typologie {
+    pub fn from_libelle(libelle: &str) -> Self {
+        match normalize(libelle).as_str() {
+            "maison" => Typologie::Maison,
+            "appartement" => Typologie::Appartement,
+            "dependance" => Typologie::Dependance,
+            "activite" => Typologie::Activite,
+            "bati mixte" => Typologie::BatiMixte,
+            "terrain" => Typologie::Terrain,
+            _ => Typologie::Other(libelle.trim().to_string()),
+        }
+    }
+
+    /// Stable code of the typologie, [OTHER_CODE] for the unknown libellés.
+    pub fn code(&self) -> u8 {
+        match self {
+            Typologie::Maison => 1,
+            Typologie::Appartement => 2,
+            Typologie::Dependance => 3,
+            Typologie::Activite => 4,
+            Typologie::BatiMixte => 5,
+            Typologie::Terrain => 6,
+            Typologie::Other(_) => OTHER_CODE,
+        }
+    }
+
+    pub fn libelle(&self) -> &str {
+        match self {
+            Typologie::Maison => "Maison",
+            Typologie::Appartement => "Appartement",
+            Typologie::Dependance => "Dépendance",
+            Typologie::Activite => "Activité",
+            Typologie::BatiMixte => "Bâti mixte",
+            Typologie::Terrain => "Terrain",
+            Typologie::Other(libelle) => libelle,
+        }
+    }
+}
+
+impl NatureMutation {
+    pub fn from_libelle(libelle: &str) -> Self {
+        match normalize(libelle).as_str() {
+            "vente" => NatureMutation::Vente,
+            "vente en l'etat futur d'achevement" => NatureMutation::VenteEnLEtatFuturDAchevement,
+            "vente terrain a batir" => NatureMutation::VenteTerrainABatir,
+            "echange" => NatureMutation::Echange,
+            "adjudication" => NatureMutation::Adjudication,
+            "expropriation" => NatureMutation::Expropriation,
+            _ => NatureMutation::Other(libelle.trim().to_string()),
+        }
+    }
+
+    /// Stable code of the nature, [OTHER_CODE] for the unknown libellés.
+    pub fn code(&self) -> u8 {
+        match self {
+            NatureMutation::Vente => 1,
+            NatureMutation::VenteEnLEtatFuturDAchevement => 2,
+            NatureMutation::VenteTerrainABatir => 3,
+            NatureMutation::Echange => 4,
+            NatureMutation::Adjudication => 5,
+            NatureMutation::Expropriation => 6,
+            NatureMutation::Other(_) => OTHER_CODE,
+        }
+    }
+
+    pub fn libelle(&self) -> &str {
+        match self {
+            NatureMutation::Vente => "Vente",
+            NatureMutation::VenteEnLEtatFuturDAchevement => "Vente en l'état futur d'achèvement",
+            NatureMutation::VenteTerrainABatir => "Vente terrain à bâtir",
+            NatureMutation::Echange => "Echange",
+            NatureMutation::Adjudication => "Adjudication",
+            NatureMutation::Expropriation => "Expropriation",
+            NatureMutation::Other(libelle) => libelle,
+        }
+    }
+}
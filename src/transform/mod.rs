@@ -1,5 +1,12 @@
+pub mod address;
 pub mod api_dvf;
+mod api_model;
+pub mod categories;
 pub mod duckdb;
 pub mod parquet_data;
+pub mod parquet_sink;
+pub mod partition;
+pub mod price;
+pub mod quarantine;
 pub mod tables;
 mod utils;
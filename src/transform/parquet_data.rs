@@ -1,169 +1,687 @@
 use arrow::{
-    array::{ArrayRef, BooleanArray, Date32Array, Float64Array, StringArray, UInt64Array},
-    datatypes::{DataType, Field, Schema},
-    record_batch::RecordBatch,
+    array::{
+        Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, DictionaryArray,
+        Float64Array, ListArray, ListBuilder, StringArray, StringBuilder, UInt8Array, UInt32Array,
+        UInt64Array,
+    },
+    datatypes::{DataType, Field, Int16Type, Schema},
+    record_batch::{RecordBatch, RecordBatchReader},
 };
-use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use chrono::NaiveDate;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rust_decimal::Decimal;
+use std::error::Error;
 use std::fs::File;
+use std::path::Path;
 use std::sync::Arc;
-use std::{error::Error, path::PathBuf};
 
-use crate::transform::utils::parse_date;
+use crate::transform::utils::{date_from_days, days_since_epoch};
 
-use super::tables::{Classes, Mutation};
+use super::categories::{NatureMutation, Typologie};
+use super::parquet_sink::{DEFAULT_WRITER_CONFIG, ParquetSink, WriterConfig};
+use super::price::PriceRule;
+
+/// Precision & scale of the decimals, consistent with the DDL of ***init.sql***.
+pub const VALEUR_FONCIERE_PRECISION: u8 = 15;
+pub const SURFACE_PRECISION: u8 = 12;
+pub const DECIMAL_SCALE: i8 = 2;
+
+/// Check the ***decimal*** rounded to [DECIMAL_SCALE] fits in a DECIMAL(***precision***, [DECIMAL_SCALE]).<br>
+/// The Decimal128 columns aren't checked when written, a larger value would make the file unreadable.
+pub fn check_precision(decimal: &Decimal, precision: u8) -> Result<(), String> {
+    let bound = Decimal::from_i128_with_scale(10i128.pow(precision as u32), DECIMAL_SCALE as u32);
+
+    if decimal.round_dp(DECIMAL_SCALE as u32).abs() < bound {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} doesn't fit in a DECIMAL({},{})",
+            decimal, precision, DECIMAL_SCALE
+        ))
+    }
+}
+
+/// Return the unscaled value of the ***decimal*** rounded to [DECIMAL_SCALE].
+fn to_decimal128(decimal: &Decimal) -> i128 {
+    let mut decimal = decimal.round_dp(DECIMAL_SCALE as u32);
+    decimal.rescale(DECIMAL_SCALE as u32);
+    decimal.mantissa()
+}
+
+/// Type of the columns with few distinct values, dictionary-encoded.
+fn dictionary_type() -> DataType {
+    DataType::Dictionary(Box::new(DataType::Int16), Box::new(DataType::Utf8))
+}
+
+fn decimal_array(values: Vec<Option<i128>>, precision: u8) -> ArrayRef {
+    Arc::new(
+        Decimal128Array::from(values)
+            .with_precision_and_scale(precision, DECIMAL_SCALE)
+            .expect("Inconsistant decimal precision or scale"),
+    )
+}
+
+/// Check the ***schema*** of a Parquet file is the ***expected*** one : same columns, types & nullability.
+fn check_schema(schema: &Schema, expected: &Schema) -> Result<(), String> {
+    if schema.fields().len() != expected.fields().len() {
+        return Err(format!(
+            "Expected {} columns, found {}",
+            expected.fields().len(),
+            schema.fields().len()
+        ));
+    }
+
+    for (field, expected) in schema.fields().iter().zip(expected.fields()) {
+        if field.name() != expected.name()
+            || field.data_type() != expected.data_type()
+            || field.is_nullable() != expected.is_nullable()
+        {
+            return Err(format!(
+                "Expected the column '{}' {} (nullable : {}), found '{}' {} (nullable : {})",
+                expected.name(),
+                expected.data_type(),
+                expected.is_nullable(),
+                field.name(),
+                field.data_type(),
+                field.is_nullable()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Return the record batches of the Parquet file ***path***, after checking its schema is the ***expected*** one.
+fn read_batches(
+    path: &Path,
+    expected: &Schema,
+) -> Result<impl Iterator<Item = Result<RecordBatch, Box<dyn Error>>> + use<>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+
+    check_schema(&reader.schema(), expected).map_err(|e| format!("{} : {}", path.display(), e))?;
+
+    Ok(reader.map(|batch| batch.map_err(Box::<dyn Error>::from)))
+}
 
 pub trait ParquetData {
+    /// Properties of the Parquet files of the table.
+    const WRITER_CONFIG: WriterConfig = DEFAULT_WRITER_CONFIG;
+
     fn to_arrays(data: &[Self]) -> Vec<ArrayRef>
     where
         Self: Sized;
 
     fn get_schema() -> Schema;
 
-    fn write_to_parquet(data: &[Self], path: &PathBuf) -> Result<(), Box<dyn Error>>
+    /// Rebuild the rows from the ***arrays***, in the order of [ParquetData::get_schema].
+    fn from_arrays(arrays: &[ArrayRef]) -> Result<Vec<Self>, String>
+    where
+        Self: Sized;
+
+    /// Read the rows of the Parquet file ***path***, one record batch at a time.<br>
+    /// The first item is an Error if the schema of the file isn't the one of [ParquetData::get_schema].
+    fn read_from_parquet(path: &Path) -> impl Iterator<Item = Result<Self, Box<dyn Error>>>
     where
         Self: Sized,
     {
-        let arrays = Self::to_arrays(data);
-        let schema = Arc::new(Self::get_schema());
-        let record_batch = RecordBatch::try_new(schema.clone(), arrays)?;
+        let batches: Box<dyn Iterator<Item = Result<RecordBatch, Box<dyn Error>>>> =
+            match read_batches(path, &Self::get_schema()) {
+                Ok(batches) => Box::new(batches),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
 
-        let file = File::create(path)?;
-        let props = WriterProperties::builder()
-            .set_compression(parquet::basic::Compression::SNAPPY)
-            .build();
+        batches.flat_map(|batch| {
+            match batch.and_then(|batch| Ok(Self::from_arrays(batch.columns())?)) {
+                Ok(rows) => rows.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e)],
+            }
+        })
+    }
 
-        let mut writer = ArrowWriter::try_new(file, schema, Some(props))?;
-        writer.write(&record_batch)?;
-        writer.close()?;
+    /// Write the ***data*** in the Parquet file ***path*** with a [ParquetSink], atomically.
+    fn write_to_parquet(data: &[Self], path: &Path) -> Result<(), Box<dyn Error>>
+    where
+        Self: Sized + Clone,
+    {
+        let mut sink = ParquetSink::new(path)?;
+        sink.write(data)?;
+        sink.finish()?;
 
         Ok(())
     }
 }
 
-impl ParquetData for Classes {
-    fn to_arrays(data: &[Self]) -> Vec<ArrayRef> {
-        let mut idg_vec = Vec::new();
-        let mut libelle_vec = Vec::new();
-        let mut surface_vec = Vec::new();
+/// Type of a field stored as a column by `#[derive(ParquetData)]`.
+pub trait ParquetColumn: Sized {
+    /// Arrow type of the column, ***precision*** is only used by the decimals.
+    fn data_type(precision: u8) -> DataType;
+
+    fn nullable() -> bool {
+        false
+    }
+
+    /// Build the column from the ***values***, ***None*** being the null values.
+    fn to_array(values: &[Option<&Self>], precision: u8) -> ArrayRef;
+
+    /// Read the values of the column, ***None*** being the null values.
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String>;
+}
+
+/// Return the ***array*** as its concrete type ***A***.
+fn downcast<A: Array + 'static>(array: &ArrayRef) -> Result<&A, String> {
+    array
+        .as_any()
+        .downcast_ref::<A>()
+        .ok_or_else(|| format!("Unexpected column type {}", array.data_type()))
+}
+
+/// Return the column ***index*** of the ***arrays*** read by `#[derive(ParquetData)]`.
+pub fn column(arrays: &[ArrayRef], index: usize) -> Result<&ArrayRef, String> {
+    arrays
+        .get(index)
+        .ok_or_else(|| format!("Missing the column {}", index))
+}
 
-        for class in data {
-            idg_vec.push(Some(class.idg));
-            libelle_vec.push(Some(class.libelle.clone()));
-            surface_vec.push(Some(class.surface));
+/// Return the field of the column ***name*** of type ***C***, nullable if ***C*** is.
+pub fn field_of<C: ParquetColumn>(name: &str, precision: u8) -> Field {
+    Field::new(name, C::data_type(precision), C::nullable())
+}
+
+/// Return the field of the column ***name*** computed by ***with*** from a field of type ***T***.
+pub fn derived_field<T, C, F>(name: &str, _with: F, precision: u8) -> Field
+where
+    C: ParquetColumn,
+    F: Fn(&T) -> C,
+{
+    field_of::<C>(name, precision)
+}
+
+/// Build the column of the ***values*** computed for a derived column.
+pub fn column_of<C: ParquetColumn>(values: &[C], precision: u8) -> ArrayRef {
+    let values = values.iter().map(Some).collect::<Vec<Option<&C>>>();
+    C::to_array(&values, precision)
+}
+
+macro_rules! primitive_column {
+    ($type:ty, $data_type:expr, $array:ty) => {
+        impl ParquetColumn for $type {
+            fn data_type(_precision: u8) -> DataType {
+                $data_type
+            }
+
+            fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+                Arc::new(<$array>::from_iter(
+                    values.iter().map(|value| value.copied()),
+                ))
+            }
+
+            fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+                Ok(downcast::<$array>(array)?.iter().collect())
+            }
         }
+    };
+}
 
-        vec![
-            Arc::new(UInt64Array::from_iter(idg_vec)),
-            Arc::new(StringArray::from_iter(libelle_vec)),
-            Arc::new(Float64Array::from_iter(surface_vec)),
-        ]
+primitive_column!(bool, DataType::Boolean, BooleanArray);
+primitive_column!(u8, DataType::UInt8, UInt8Array);
+primitive_column!(u32, DataType::UInt32, UInt32Array);
+primitive_column!(u64, DataType::UInt64, UInt64Array);
+primitive_column!(f64, DataType::Float64, Float64Array);
+
+impl<T: ParquetColumn> ParquetColumn for Option<T> {
+    fn data_type(precision: u8) -> DataType {
+        T::data_type(precision)
     }
 
-    fn get_schema() -> Schema {
-        Schema::new(vec![
-            Field::new("idg", DataType::UInt64, false),
-            Field::new("libelle", DataType::Utf8, false),
-            Field::new("surface", DataType::Float64, false),
-        ])
-    }
-}
-
-impl ParquetData for Mutation {
-    fn to_arrays(data: &[Self]) -> Vec<ArrayRef> {
-        let mut idg_vec = Vec::new();
-        let mut idpar_vec = Vec::new();
-        let mut idmutation_vec = Vec::new();
-        let mut vefa_vec = Vec::new();
-        let mut typologie_vec = Vec::new();
-        let mut datemut_vec = Vec::new();
-        let mut nature_vec = Vec::new();
-        let mut btq_vec = Vec::new();
-        let mut voie_vec = Vec::new();
-        let mut novoie_vec = Vec::new();
-        let mut codvoie_vec = Vec::new();
-        let mut commune_vec = Vec::new();
-        let mut typvoie_vec = Vec::new();
-        let mut codepostal_vec = Vec::new();
-        let mut valeur_fonciere_vec = Vec::new();
-        let mut vendu_vec = Vec::new();
-
-        for mutation in data {
-            idg_vec.push(mutation.idg);
-            idpar_vec.push(mutation.idpar.clone());
-            idmutation_vec.push(mutation.idmutation);
-
-            vefa_vec.push(mutation.shared_props.vefa);
-            typologie_vec.push(mutation.shared_props.typologie.clone());
-            datemut_vec.push(parse_date(&mutation.shared_props.datemut));
-            nature_vec.push(mutation.shared_props.nature.clone());
-
-            btq_vec.push(mutation.adresse.btq.clone());
-            voie_vec.push(mutation.adresse.voie.clone());
-            novoie_vec.push(mutation.adresse.novoie.clone());
-            codvoie_vec.push(mutation.adresse.codvoie.clone());
-            commune_vec.push(mutation.adresse.commune.clone());
-            typvoie_vec.push(mutation.adresse.typvoie.clone());
-            codepostal_vec.push(mutation.adresse.codepostal.clone());
-
-            valeur_fonciere_vec.push(mutation.valeur_fonciere);
-            vendu_vec.push(mutation.vendu);
+    fn nullable() -> bool {
+        true
+    }
+
+    fn to_array(values: &[Option<&Self>], precision: u8) -> ArrayRef {
+        let values = values
+            .iter()
+            .map(|value| value.and_then(Option::as_ref))
+            .collect::<Vec<Option<&T>>>();
+        T::to_array(&values, precision)
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        Ok(T::from_array(array)?.into_iter().map(Some).collect())
+    }
+}
+
+impl ParquetColumn for String {
+    fn data_type(_precision: u8) -> DataType {
+        DataType::Utf8
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(StringArray::from_iter(
+            values.iter().map(|value| value.map(String::as_str)),
+        ))
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        Ok(downcast::<StringArray>(array)?
+            .iter()
+            .map(|value| value.map(String::from))
+            .collect())
+    }
+}
+
+impl ParquetColumn for Decimal {
+    fn data_type(precision: u8) -> DataType {
+        DataType::Decimal128(precision, DECIMAL_SCALE)
+    }
+
+    fn to_array(values: &[Option<&Self>], precision: u8) -> ArrayRef {
+        decimal_array(
+            values
+                .iter()
+                .map(|value| value.map(to_decimal128))
+                .collect(),
+            precision,
+        )
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        let array = downcast::<Decimal128Array>(array)?;
+        let scale = u32::try_from(array.scale()).map_err(|e| e.to_string())?;
+
+        Ok(array
+            .iter()
+            .map(|value| value.map(|value| Decimal::from_i128_with_scale(value, scale)))
+            .collect())
+    }
+}
+
+impl ParquetColumn for NaiveDate {
+    fn data_type(_precision: u8) -> DataType {
+        DataType::Date32
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(Date32Array::from_iter(
+            values.iter().map(|value| value.map(days_since_epoch)),
+        ))
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        downcast::<Date32Array>(array)?
+            .iter()
+            .map(|value| {
+                value
+                    .map(|days| date_from_days(days).ok_or(format!("Invalid date {}", days)))
+                    .transpose()
+            })
+            .collect()
+    }
+}
+
+/// Binary column, e.g. the WKB geometries.
+impl ParquetColumn for Vec<u8> {
+    fn data_type(_precision: u8) -> DataType {
+        DataType::Binary
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(BinaryArray::from_iter(
+            values.iter().map(|value| value.map(Vec::as_slice)),
+        ))
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        Ok(downcast::<BinaryArray>(array)?
+            .iter()
+            .map(|value| value.map(<[u8]>::to_vec))
+            .collect())
+    }
+}
+
+impl ParquetColumn for Vec<String> {
+    fn data_type(_precision: u8) -> DataType {
+        DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+
+        for value in values {
+            match value {
+                Some(value) => builder.append_value(value.iter().map(|item| Some(item.as_str()))),
+                None => builder.append_null(),
+            }
         }
+        Arc::new(builder.finish())
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        downcast::<ListArray>(array)?
+            .iter()
+            .map(|value| {
+                value
+                    .map(|items| {
+                        Ok(downcast::<StringArray>(&items)?
+                            .iter()
+                            .flatten()
+                            .map(String::from)
+                            .collect())
+                    })
+                    .transpose()
+            })
+            .collect()
+    }
+}
+
+/// Read the values of a dictionary-encoded column.
+fn read_dictionary(array: &ArrayRef) -> Result<Vec<Option<&str>>, String> {
+    let array = downcast::<DictionaryArray<Int16Type>>(array)?
+        .downcast_dict::<StringArray>()
+        .ok_or("Unexpected dictionary values")?;
+
+    Ok(array.into_iter().collect())
+}
+
+impl ParquetColumn for Typologie {
+    fn data_type(_precision: u8) -> DataType {
+        dictionary_type()
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(
+            values
+                .iter()
+                .map(|value| value.map(Typologie::libelle))
+                .collect::<DictionaryArray<Int16Type>>(),
+        )
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        Ok(read_dictionary(array)?
+            .into_iter()
+            .map(|value| value.map(Typologie::from_libelle))
+            .collect())
+    }
+}
+
+impl ParquetColumn for NatureMutation {
+    fn data_type(_precision: u8) -> DataType {
+        dictionary_type()
+    }
+
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(
+            values
+                .iter()
+                .map(|value| value.map(NatureMutation::libelle))
+                .collect::<DictionaryArray<Int16Type>>(),
+        )
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        Ok(read_dictionary(array)?
+            .into_iter()
+            .map(|value| value.map(NatureMutation::from_libelle))
+            .collect())
+    }
+}
+
+impl ParquetColumn for PriceRule {
+    fn data_type(_precision: u8) -> DataType {
+        DataType::Utf8
+    }
 
-        let idg_arr = Arc::new(UInt64Array::from(idg_vec)) as ArrayRef;
-        let idpar_arr = Arc::new(StringArray::from(idpar_vec)) as ArrayRef;
-        let idmutation_arr = Arc::new(UInt64Array::from(idmutation_vec)) as ArrayRef;
-        let vefa_arr = Arc::new(BooleanArray::from(vefa_vec)) as ArrayRef;
-        let typologie_arr = Arc::new(StringArray::from(typologie_vec)) as ArrayRef;
-        let datemut_arr = Arc::new(Date32Array::from_iter(datemut_vec)) as ArrayRef;
-        let nature_arr = Arc::new(StringArray::from(nature_vec)) as ArrayRef;
-        let btq_arr = Arc::new(StringArray::from_iter(btq_vec)) as ArrayRef;
-        let voie_arr = Arc::new(StringArray::from_iter(voie_vec)) as ArrayRef;
-        let novoie_arr = Arc::new(StringArray::from_iter(novoie_vec)) as ArrayRef;
-        let codvoie_arr = Arc::new(StringArray::from_iter(codvoie_vec)) as ArrayRef;
-        let commune_arr = Arc::new(StringArray::from_iter(commune_vec)) as ArrayRef;
-        let typvoie_arr = Arc::new(StringArray::from_iter(typvoie_vec)) as ArrayRef;
-        let codepostal_arr = Arc::new(StringArray::from_iter(codepostal_vec)) as ArrayRef;
-        let valeur_fonciere_arr = Arc::new(Float64Array::from(valeur_fonciere_vec)) as ArrayRef;
-        let vendu_arr = Arc::new(BooleanArray::from(vendu_vec)) as ArrayRef;
+    fn to_array(values: &[Option<&Self>], _precision: u8) -> ArrayRef {
+        Arc::new(StringArray::from_iter(
+            values.iter().map(|value| value.map(PriceRule::as_str)),
+        ))
+    }
+
+    fn from_array(array: &ArrayRef) -> Result<Vec<Option<Self>>, String> {
+        downcast::<StringArray>(array)?
+            .iter()
+            .map(|value| value.map(str::parse::<PriceRule>).transpose())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::transform::tables::{Classes, FeatureSource, Mutation};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("data-immo-{}-{}.parquet", name, std::process::id()))
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
 
+    fn mutations() -> Vec<Mutation> {
         vec![
-            idg_arr,
-            idpar_arr,
-            idmutation_arr,
-            vefa_arr,
-            typologie_arr,
-            datemut_arr,
-            nature_arr,
-            btq_arr,
-            voie_arr,
-            novoie_arr,
-            codvoie_arr,
-            commune_arr,
-            typvoie_arr,
-            codepostal_arr,
-            valeur_fonciere_arr,
-            vendu_arr,
+            Mutation {
+                idm: 1,
+                idmutation: 42,
+                vefa: true,
+                typologie: Typologie::Appartement,
+                datemut: NaiveDate::from_ymd_opt(2023, 5, 17).unwrap(),
+                nature: NatureMutation::Vente,
+                codtypbien: Some("121".to_string()),
+                libtypbien: Some("UN APPARTEMENT".to_string()),
+                sbati: Some(decimal("54.20")),
+                sterr: Some(decimal("1234567890.05")),
+                nblocmut: Some(2),
+                nbpar: Some(1),
+                coddep: Some("75".to_string()),
+                l_codinsee: Some(vec!["75102".to_string(), "75103".to_string()]),
+                longitude: Some(2.35),
+                latitude: Some(48.85),
+                geometry: Some(vec![1, 3, 0, 0, 0]),
+                source: FeatureSource {
+                    code_insee: Some("75102".to_string()),
+                    nom_commune: Some("Paris 2e".to_string()),
+                    code_dep: Some("75".to_string()),
+                },
+            },
+            Mutation {
+                idm: 2,
+                idmutation: 43,
+                vefa: false,
+                typologie: Typologie::Other("Château".to_string()),
+                datemut: NaiveDate::from_ymd_opt(1965, 12, 31).unwrap(),
+                nature: NatureMutation::Adjudication,
+                codtypbien: None,
+                libtypbien: None,
+                sbati: None,
+                sterr: None,
+                nblocmut: None,
+                nbpar: None,
+                coddep: None,
+                l_codinsee: None,
+                longitude: None,
+                latitude: None,
+                geometry: None,
+                source: FeatureSource::default(),
+            },
         ]
     }
 
-    fn get_schema() -> Schema {
-        Schema::new(vec![
-            Field::new("idg", DataType::UInt64, false),
-            Field::new("idpar", DataType::Utf8, false),
-            Field::new("idmutation", DataType::UInt64, false),
-            Field::new("vefa", DataType::Boolean, false),
-            Field::new("typologie", DataType::Utf8, true),
-            Field::new("datemut", DataType::Date32, false),
-            Field::new("nature", DataType::Utf8, true),
-            Field::new("btq", DataType::Utf8, true),
-            Field::new("voie", DataType::Utf8, true),
-            Field::new("novoie", DataType::Utf8, true),
-            Field::new("codvoie", DataType::Utf8, true),
-            Field::new("commune", DataType::Utf8, true),
-            Field::new("typvoie", DataType::Utf8, true),
-            Field::new("codepostal", DataType::Utf8, true),
-            Field::new("valeur_fonciere", DataType::Float64, false),
-            Field::new("vendu", DataType::Boolean, true),
-        ])
+    fn read<T: ParquetData>(path: &Path) -> Vec<T> {
+        T::read_from_parquet(path)
+            .collect::<Result<Vec<T>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn mutations_round_trip() {
+        let path = temp_path("mutations");
+        let expected = mutations();
+
+        ParquetData::write_to_parquet(&expected, &path).unwrap();
+        let rows = read::<Mutation>(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(rows.len(), expected.len());
+        for (row, expected) in rows.iter().zip(&expected) {
+            assert_eq!(row.idm, expected.idm);
+            assert_eq!(row.idmutation, expected.idmutation);
+            assert_eq!(row.vefa, expected.vefa);
+            assert_eq!(row.typologie, expected.typologie);
+            assert_eq!(row.datemut, expected.datemut);
+            assert_eq!(row.nature, expected.nature);
+            assert_eq!(row.codtypbien, expected.codtypbien);
+            assert_eq!(row.libtypbien, expected.libtypbien);
+            assert_eq!(row.sbati, expected.sbati);
+            assert_eq!(row.sterr, expected.sterr);
+            assert_eq!(row.nblocmut, expected.nblocmut);
+            assert_eq!(row.nbpar, expected.nbpar);
+            assert_eq!(row.coddep, expected.coddep);
+            assert_eq!(row.l_codinsee, expected.l_codinsee);
+            assert_eq!(row.longitude, expected.longitude);
+            assert_eq!(row.latitude, expected.latitude);
+            assert_eq!(row.geometry, expected.geometry);
+            assert_eq!(row.source.code_insee, expected.source.code_insee);
+            assert_eq!(row.source.nom_commune, expected.source.nom_commune);
+            assert_eq!(row.source.code_dep, expected.source.code_dep);
+        }
+    }
+
+    #[test]
+    fn mutations_schema() {
+        let schema = Mutation::get_schema();
+
+        assert_eq!(
+            schema.field_with_name("typologie").unwrap().data_type(),
+            &dictionary_type()
+        );
+        assert_eq!(
+            schema
+                .field_with_name("code_typologie")
+                .unwrap()
+                .data_type(),
+            &DataType::UInt8
+        );
+        assert_eq!(
+            schema.field_with_name("sbati").unwrap().data_type(),
+            &DataType::Decimal128(SURFACE_PRECISION, DECIMAL_SCALE)
+        );
+        assert_eq!(
+            schema.field_with_name("datemut").unwrap().data_type(),
+            &DataType::Date32
+        );
+        assert!(
+            schema
+                .field_with_name("source_code_dep")
+                .unwrap()
+                .is_nullable()
+        );
+        assert!(!schema.field_with_name("idm").unwrap().is_nullable());
+    }
+
+    #[test]
+    fn classes_round_trip() {
+        let path = temp_path("classes");
+        let expected = vec![
+            Classes {
+                idg: 7,
+                rank: 0,
+                libelle: "Sols".to_string(),
+                surface: decimal("54.20"),
+            },
+            Classes {
+                idg: 7,
+                rank: 1,
+                libelle: "Dépendance".to_string(),
+                surface: decimal("8"),
+            },
+        ];
+
+        ParquetData::write_to_parquet(&expected, &path).unwrap();
+        let rows = read::<Classes>(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(rows.len(), expected.len());
+        for (row, expected) in rows.iter().zip(&expected) {
+            assert_eq!(row.idg, expected.idg);
+            assert_eq!(row.rank, expected.rank);
+            assert_eq!(row.libelle, expected.libelle);
+            assert_eq!(row.surface, expected.surface);
+        }
+    }
+
+    /// Columns named after the ***rename*** of the fields.
+    #[derive(Debug, Clone, PartialEq, parquet_data_derive::ParquetData)]
+    struct Renamed {
+        #[parquet(rename = "code_insee")]
+        insee: String,
+        #[parquet(rename = "surface_m2", precision = 12)]
+        surface: Option<Decimal>,
+    }
+
+    #[test]
+    fn renamed_columns() {
+        let schema = Renamed::get_schema();
+        let names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(names, ["code_insee", "surface_m2"]);
+        assert_eq!(
+            schema.field(1).data_type(),
+            &DataType::Decimal128(12, DECIMAL_SCALE)
+        );
+        assert!(!schema.field(0).is_nullable());
+        assert!(schema.field(1).is_nullable());
+
+        let path = temp_path("renamed");
+        let expected = vec![
+            Renamed {
+                insee: "2A004".to_string(),
+                surface: Some(decimal("54.20")),
+            },
+            Renamed {
+                insee: "75102".to_string(),
+                surface: None,
+            },
+        ];
+
+        ParquetData::write_to_parquet(&expected, &path).unwrap();
+        let rows = read::<Renamed>(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn decimal_precision() {
+        assert!(check_precision(&decimal("9999999999999.99"), VALEUR_FONCIERE_PRECISION).is_ok());
+        assert!(check_precision(&decimal("-9999999999999.99"), VALEUR_FONCIERE_PRECISION).is_ok());
+        assert!(check_precision(&decimal("10000000000000"), VALEUR_FONCIERE_PRECISION).is_err());
+        // Rounded to the scale of the column before the check
+        assert!(check_precision(&decimal("9999999999.994"), SURFACE_PRECISION).is_ok());
+        assert!(check_precision(&decimal("9999999999.995"), SURFACE_PRECISION).is_err());
+    }
+
+    #[test]
+    fn mismatched_schema_is_an_error() {
+        let path = temp_path("mismatched");
+        let classes = vec![Classes {
+            idg: 1,
+            rank: 0,
+            libelle: "Sols".to_string(),
+            surface: decimal("10"),
+        }];
+
+        ParquetData::write_to_parquet(&classes, &path).unwrap();
+        let first = Mutation::read_from_parquet(&path).next();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(matches!(first, Some(Err(_))));
     }
 }
@@ -0,0 +1,408 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+use arrow::datatypes::Schema;
+use arrow::record_batch::RecordBatch;
+use mylog::error;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::{EnabledStatistics, WriterProperties},
+    schema::types::ColumnPath,
+};
+
+use super::parquet_data::ParquetData;
+
+/// Compression codec of the Parquet files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Uncompressed,
+    Snappy,
+    Lz4,
+    /// ZSTD with its level, from 1 (fastest) to 22 (smallest).
+    Zstd(i32),
+}
+
+/// Properties of the Parquet files written for a table, see [ParquetData::WRITER_CONFIG].
+#[derive(Debug, Clone, Copy)]
+pub struct WriterConfig {
+    pub codec: Codec,
+    pub dictionary: bool,
+    /// Level of the min/max statistics, the page statistics let the query engines skip pages.
+    pub statistics: EnabledStatistics,
+    pub row_group_size: usize,
+    /// Columns with a bloom filter, the high-cardinality columns used in the lookups.
+    pub bloom_filter_columns: &'static [&'static str],
+}
+
+pub const DEFAULT_WRITER_CONFIG: WriterConfig = WriterConfig {
+    codec: Codec::Snappy,
+    dictionary: true,
+    statistics: EnabledStatistics::Page,
+    row_group_size: 100_000,
+    bloom_filter_columns: &[],
+};
+
+/// The WKB of the geometries are large and compress well with ZSTD.
+pub const MUTATIONS_WRITER_CONFIG: WriterConfig = WriterConfig {
+    codec: Codec::Zstd(3),
+    ..DEFAULT_WRITER_CONFIG
+};
+
+pub const PARCELLES_WRITER_CONFIG: WriterConfig = WriterConfig {
+    bloom_filter_columns: &["idpar"],
+    ..DEFAULT_WRITER_CONFIG
+};
+
+pub const ADRESSES_WRITER_CONFIG: WriterConfig = WriterConfig {
+    bloom_filter_columns: &["codepostal", "commune"],
+    ..DEFAULT_WRITER_CONFIG
+};
+
+/// Argument of the command line setting the codec of every table, e.g. *'--codec=lz4'* or *'--codec=zstd:9'*.
+const CODEC_ARG: &str = "--codec=";
+
+/// Level of ***zstd*** when the argument doesn't set it, as the ***MUTATIONS_WRITER_CONFIG***.
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+impl Codec {
+    fn compression(&self) -> Result<Compression, Box<dyn Error>> {
+        Ok(match self {
+            Codec::Uncompressed => Compression::UNCOMPRESSED,
+            Codec::Snappy => Compression::SNAPPY,
+            Codec::Lz4 => Compression::LZ4_RAW,
+            Codec::Zstd(level) => Compression::ZSTD(ZstdLevel::try_new(*level)?),
+        })
+    }
+
+    /// Return the codec set by ***CODEC_ARG*** in the arguments of the command line, if any.<br>
+    /// An invalid codec is logged once and the codecs of the tables are kept.
+    fn from_args() -> Option<Codec> {
+        static CODEC: OnceLock<Option<Codec>> = OnceLock::new();
+
+        *CODEC.get_or_init(|| {
+            let value =
+                std::env::args().find_map(|arg| arg.strip_prefix(CODEC_ARG).map(String::from))?;
+
+            value
+                .parse::<Codec>()
+                .map_err(|e| error!("Invalid argument {}{} : {}", CODEC_ARG, value, e))
+                .ok()
+        })
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    /// Parse *'uncompressed'*, *'snappy'*, *'lz4'*, *'zstd'* or *'zstd:LEVEL'*.
+    fn from_str(codec: &str) -> Result<Self, Self::Err> {
+        match codec.split_once(':') {
+            None => match codec {
+                "uncompressed" => Ok(Codec::Uncompressed),
+                "snappy" => Ok(Codec::Snappy),
+                "lz4" => Ok(Codec::Lz4),
+                "zstd" => Ok(Codec::Zstd(DEFAULT_ZSTD_LEVEL)),
+                codec => Err(format!("Unknown codec {}", codec)),
+            },
+            Some(("zstd", level)) => {
+                let level = level
+                    .parse::<i32>()
+                    .map_err(|e| format!("Invalid zstd level {} : {}", level, e))?;
+                ZstdLevel::try_new(level).map_err(|e| e.to_string())?;
+                Ok(Codec::Zstd(level))
+            }
+            Some(_) => Err(format!("Only zstd has a level, found {}", codec)),
+        }
+    }
+}
+
+impl WriterConfig {
+    pub fn properties(&self) -> Result<WriterProperties, Box<dyn Error>> {
+        let mut builder = WriterProperties::builder()
+            .set_compression(self.codec.compression()?)
+            .set_dictionary_enabled(self.dictionary)
+            .set_statistics_enabled(self.statistics)
+            .set_max_row_group_size(self.row_group_size.max(1));
+
+        for column in self.bloom_filter_columns {
+            builder =
+                builder.set_column_bloom_filter_enabled(ColumnPath::from(column.to_string()), true);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Extension added to the file while it's written, the file is renamed once complete.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Parquet file kept open to write the rows of ***T*** batch after batch.<br>
+/// The rows are buffered and written by row groups of ***row_group_size*** rows, see [WriterConfig].<br>
+/// The file is written next to its ***path*** (e.g. *'mutations_1.parquet.tmp'*) and renamed by [ParquetSink::finish],
+/// so a crash never leaves a truncated *'.parquet'* file.
+pub struct ParquetSink<T: ParquetData + Clone> {
+    path: PathBuf,
+    temp_path: PathBuf,
+    schema: Arc<Schema>,
+    writer: Option<ArrowWriter<File>>,
+    buffer: Vec<T>,
+    row_group_size: usize,
+    rows: usize,
+    /// Set once the file is renamed, until then the temporary file is removed on drop.
+    finished: bool,
+}
+
+impl<T: ParquetData + Clone> ParquetSink<T> {
+    /// Open the sink with the [ParquetData::WRITER_CONFIG] of the table, or the codec of ***CODEC_ARG***.
+    pub fn new(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let config = WriterConfig {
+            codec: Codec::from_args().unwrap_or(T::WRITER_CONFIG.codec),
+            ..T::WRITER_CONFIG
+        };
+        Self::with_config(path, &config)
+    }
+
+    /// Open the sink with the ***config***, its bloom filter columns must be columns of the table.
+    pub fn with_config(path: &Path, config: &WriterConfig) -> Result<Self, Box<dyn Error>> {
+        let row_group_size = config.row_group_size.max(1);
+        let temp_path = PathBuf::from(format!("{}.{}", path.display(), TEMP_EXTENSION));
+        let schema = Arc::new(T::get_schema());
+
+        // A misspelled column would silently write the file without its bloom filter
+        for column in config.bloom_filter_columns {
+            if schema.field_with_name(column).is_err() {
+                return Err(format!(
+                    "The bloom filter column '{}' isn't a column of the table",
+                    column
+                )
+                .into());
+            }
+        }
+
+        let props = config.properties()?;
+        let file = File::create(&temp_path)?;
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp_path,
+            schema,
+            writer: Some(writer),
+            buffer: Vec::new(),
+            row_group_size,
+            rows: 0,
+            finished: false,
+        })
+    }
+
+    /// Write the ***rows***, the last ones are kept in the buffer until a row group is full.
+    pub fn write(&mut self, mut rows: &[T]) -> Result<(), Box<dyn Error>> {
+        if !self.buffer.is_empty() {
+            let missing = (self.row_group_size - self.buffer.len()).min(rows.len());
+            self.buffer.extend_from_slice(&rows[..missing]);
+            rows = &rows[missing..];
+
+            if self.buffer.len() == self.row_group_size {
+                let buffer = std::mem::take(&mut self.buffer);
+                self.write_row_group(&buffer)?;
+            }
+        }
+
+        let mut chunks = rows.chunks_exact(self.row_group_size);
+        for chunk in &mut chunks {
+            self.write_row_group(chunk)?;
+        }
+        self.buffer.extend_from_slice(chunks.remainder());
+
+        Ok(())
+    }
+
+    fn write_row_group(&mut self, rows: &[T]) -> Result<(), Box<dyn Error>> {
+        let writer = self.writer.as_mut().ok_or("The Parquet sink is closed")?;
+        let record_batch = RecordBatch::try_new(self.schema.clone(), T::to_arrays(rows))?;
+
+        writer.write(&record_batch)?;
+        writer.flush()?;
+        self.rows += rows.len();
+
+        Ok(())
+    }
+
+    /// Write the buffered rows, close the file and rename it to its final path.<br>
+    /// Return the number of rows written.
+    pub fn finish(mut self) -> Result<usize, Box<dyn Error>> {
+        let buffer = std::mem::take(&mut self.buffer);
+        if !buffer.is_empty() {
+            self.write_row_group(&buffer)?;
+        }
+
+        let writer = self.writer.take().ok_or("The Parquet sink is closed")?;
+        let file = writer.into_inner()?;
+        file.sync_all()?;
+
+        fs::rename(&self.temp_path, &self.path)?;
+        self.finished = true;
+
+        Ok(self.rows)
+    }
+}
+
+impl<T: ParquetData + Clone> Drop for ParquetSink<T> {
+    /// Remove the incomplete file of a sink dropped without [ParquetSink::finish], or after it failed.
+    fn drop(&mut self) {
+        if !self.finished {
+            drop(self.writer.take());
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::transform::tables::{Adresses, Classes};
+
+    const SMALL_ROW_GROUPS: WriterConfig = WriterConfig {
+        row_group_size: 4,
+        ..DEFAULT_WRITER_CONFIG
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "data-immo-sink-{}-{}.parquet",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn classes(size: usize) -> Vec<Classes> {
+        (0..size)
+            .map(|rank| Classes {
+                idg: 1,
+                rank: rank as u32,
+                libelle: "Sols".to_string(),
+                surface: Decimal::from(rank),
+            })
+            .collect()
+    }
+
+    /// Return the number of rows of each row group of the Parquet file ***path***, from its metadata.
+    fn row_groups(path: &Path) -> Vec<i64> {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        reader
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|row_group| row_group.num_rows())
+            .collect()
+    }
+
+    #[test]
+    fn full_row_groups_are_written_by_write() {
+        let path = temp_path("row-groups");
+        let mut sink = ParquetSink::<Classes>::with_config(&path, &SMALL_ROW_GROUPS).unwrap();
+
+        sink.write(&classes(3)).unwrap();
+        assert_eq!(sink.rows, 0);
+        sink.write(&classes(6)).unwrap();
+        assert_eq!(sink.rows, 8);
+        sink.write(&classes(2)).unwrap();
+        assert_eq!(sink.rows, 8);
+        assert_eq!(sink.buffer.len(), 3);
+
+        // The remainder is written by finish
+        assert_eq!(sink.finish().unwrap(), 11);
+        let row_groups = row_groups(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(row_groups, [4, 4, 3]);
+    }
+
+    #[test]
+    fn exact_row_groups_without_remainder() {
+        let path = temp_path("exact");
+        let mut sink = ParquetSink::<Classes>::with_config(&path, &SMALL_ROW_GROUPS).unwrap();
+
+        sink.write(&classes(8)).unwrap();
+        assert!(sink.buffer.is_empty());
+
+        assert_eq!(sink.finish().unwrap(), 8);
+        let row_groups = row_groups(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(row_groups, [4, 4]);
+    }
+
+    #[test]
+    fn unfinished_sink_leaves_no_file() {
+        let path = temp_path("unfinished");
+        let temp_path = PathBuf::from(format!("{}.{}", path.display(), TEMP_EXTENSION));
+        let mut sink = ParquetSink::<Classes>::with_config(&path, &SMALL_ROW_GROUPS).unwrap();
+
+        sink.write(&classes(6)).unwrap();
+        assert!(fs::exists(&temp_path).unwrap());
+
+        drop(sink);
+        assert!(!fs::exists(&temp_path).unwrap());
+        assert!(!fs::exists(&path).unwrap());
+    }
+
+    #[test]
+    fn parsed_codecs() {
+        assert_eq!("uncompressed".parse::<Codec>(), Ok(Codec::Uncompressed));
+        assert_eq!("snappy".parse::<Codec>(), Ok(Codec::Snappy));
+        assert_eq!("lz4".parse::<Codec>(), Ok(Codec::Lz4));
+        assert_eq!("zstd".parse::<Codec>(), Ok(Codec::Zstd(DEFAULT_ZSTD_LEVEL)));
+        assert_eq!("zstd:9".parse::<Codec>(), Ok(Codec::Zstd(9)));
+
+        for codec in ["gzip", "zstd:", "zstd:99", "lz4:1", ""] {
+            assert!(codec.parse::<Codec>().is_err(), "{}", codec);
+        }
+    }
+
+    #[test]
+    fn files_are_written_with_every_codec() {
+        let path = temp_path("codecs");
+
+        for codec in [
+            Codec::Uncompressed,
+            Codec::Snappy,
+            Codec::Lz4,
+            Codec::Zstd(1),
+        ] {
+            let config = WriterConfig {
+                codec,
+                ..SMALL_ROW_GROUPS
+            };
+            let mut sink = ParquetSink::<Classes>::with_config(&path, &config).unwrap();
+            sink.write(&classes(5)).unwrap();
+            assert_eq!(sink.finish().unwrap(), 5);
+
+            let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+            let column = reader.metadata().row_group(0).column(0).compression();
+            assert_eq!(column, codec.compression().unwrap(), "{:?}", codec);
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn bloom_filter_columns_are_checked() {
+        let path =
+            std::env::temp_dir().join(format!("data-immo-bloom-{}.parquet", std::process::id()));
+
+        assert!(ParquetSink::<Classes>::with_config(&path, &ADRESSES_WRITER_CONFIG).is_err());
+
+        let sink = ParquetSink::<Adresses>::with_config(&path, &ADRESSES_WRITER_CONFIG).unwrap();
+        assert_eq!(sink.finish().unwrap(), 0);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Datelike;
+
+use super::tables::{Mutation, Tables};
+
+/// Layout of the Parquet files written by the extraction and the DuckDB export.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputLayout {
+    /// One file per table, e.g. *'mutations_{feature_id}.parquet'* & *'mutations.parquet'*.
+    Flat,
+    /// Hive partitions by department and year, e.g. *'mutations/dep=75/year=2023/part-{feature_id}.parquet'*,
+    /// the query engines skip the partitions filtered out.
+    Partitioned,
+}
+
+/// Layout by default, see [OutputLayout::from_args].
+const OUTPUT_LAYOUT: OutputLayout = OutputLayout::Flat;
+
+/// Argument of the command line to write the Parquet files in [OutputLayout::Partitioned].
+const PARTITIONED_ARG: &str = "--partitioned";
+
+impl OutputLayout {
+    /// Return the layout selected by the arguments of the command line, ***OUTPUT_LAYOUT*** by default.<br>
+    /// The extraction and the DuckDB export must run with the same arguments.
+    pub fn from_args() -> OutputLayout {
+        if std::env::args().any(|arg| arg == PARTITIONED_ARG) {
+            OutputLayout::Partitioned
+        } else {
+            OUTPUT_LAYOUT
+        }
+    }
+
+    /// Return the name of the Parquet file of the ***table*** for the feature ***feature_id***.
+    pub fn file_name(&self, table: &str, feature_id: &str) -> String {
+        match self {
+            OutputLayout::Flat => format!("{}_{}.parquet", table, feature_id),
+            OutputLayout::Partitioned => format!("part-{}.parquet", feature_id),
+        }
+    }
+
+    /// Return the Parquet files of the ***table*** written for the feature ***feature_id*** in the ***folder***.
+    pub fn feature_files(
+        &self,
+        folder: &Path,
+        table: &str,
+        feature_id: &str,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        let file_name = self.file_name(table, feature_id);
+
+        match self {
+            OutputLayout::Flat => {
+                let path = folder.join(file_name);
+                Ok(fs::exists(&path)?.then_some(path).into_iter().collect())
+            }
+            OutputLayout::Partitioned => find_parts(folder, table, &file_name),
+        }
+    }
+}
+
+/// Columns of the Hive partitions, in the order of the folders.
+pub const PARTITION_COLUMNS: [&str; 2] = ["dep", "year"];
+
+/// Value of the partition of the mutations without department, as written by DuckDB.
+pub const NULL_PARTITION: &str = "NULL";
+
+/// Partition of a mutation, the rows of its child tables are in the same partition.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PartitionKey {
+    pub dep: Option<String>,
+    pub year: i32,
+}
+
+impl PartitionKey {
+    /// The department is the ***coddep*** of the mutation, or the one of the feature used to query it.
+    pub fn of(mutation: &Mutation) -> Self {
+        Self {
+            dep: mutation
+                .coddep
+                .clone()
+                .or_else(|| mutation.source.code_dep.clone()),
+            year: mutation.datemut.year(),
+        }
+    }
+
+    /// Return the folder of the partition of the ***table***, e.g. *'{folder}/mutations/dep=75/year=2023'*.
+    pub fn folder(&self, folder: &Path, table: &str) -> PathBuf {
+        folder
+            .join(table)
+            .join(format!(
+                "{}={}",
+                PARTITION_COLUMNS[0],
+                self.dep.as_deref().unwrap_or(NULL_PARTITION)
+            ))
+            .join(format!("{}={}", PARTITION_COLUMNS[1], self.year))
+    }
+}
+
+/// Return the file ***file_name*** of every partition of the ***table***.
+fn find_parts(folder: &Path, table: &str, file_name: &str) -> std::io::Result<Vec<PathBuf>> {
+    let table_folder = folder.join(table);
+    let mut parts = Vec::new();
+    if !fs::exists(&table_folder)? {
+        return Ok(parts);
+    }
+
+    for dep in fs::read_dir(&table_folder)?.flatten() {
+        if !dep.path().is_dir() {
+            continue;
+        }
+
+        for year in fs::read_dir(dep.path())?.flatten() {
+            let path = year.path().join(file_name);
+            if fs::exists(&path)? {
+                parts.push(path);
+            }
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Remove the Parquet files of every table written for the feature ***feature_id*** in the ***folder***, in both layouts,
+/// e.g. by a previous run before the feature lost its rows or its mutations moved to other partitions.
+pub fn remove_feature_files(folder: &Path, feature_id: &str) -> std::io::Result<()> {
+    for layout in [OutputLayout::Flat, OutputLayout::Partitioned] {
+        for table in Tables::NAMES {
+            for path in layout.feature_files(folder, table, feature_id)? {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split the ***tables*** by the [PartitionKey] of their mutations.<br>
+/// The child rows follow their mutation through ***idm***, ***idd*** and ***idg***,
+/// the rows without parent are skipped (the transformation never builds them).
+pub fn partition_tables(tables: &Tables) -> BTreeMap<PartitionKey, Tables> {
+    let mut partitions: BTreeMap<PartitionKey, Tables> = BTreeMap::new();
+    let mut mutations = HashMap::new();
+    let mut dispositions = HashMap::new();
+    let mut parcelles = HashMap::new();
+
+    for mutation in &tables.mutations {
+        let key = PartitionKey::of(mutation);
+        mutations.insert(mutation.idm, key.clone());
+        partitions
+            .entry(key)
+            .or_default()
+            .mutations
+            .push(mutation.clone());
+    }
+
+    for disposition in &tables.dispositions {
+        if let Some(key) = mutations.get(&disposition.idm) {
+            dispositions.insert(disposition.idd, key);
+            partitions
+                .entry(key.clone())
+                .or_default()
+                .dispositions
+                .push(disposition.clone());
+        }
+    }
+
+    for parcelle in &tables.parcelles {
+        if let Some(key) = dispositions.get(&parcelle.idd) {
+            parcelles.insert(parcelle.idg, *key);
+            partitions
+                .entry((*key).clone())
+                .or_default()
+                .parcelles
+                .push(parcelle.clone());
+        }
+    }
+
+    for class in &tables.classes {
+        if let Some(key) = parcelles.get(&class.idg) {
+            partitions
+                .entry((*key).clone())
+                .or_default()
+                .classes
+                .push(class.clone());
+        }
+    }
+
+    for adresse in &tables.adresses {
+        if let Some(key) = parcelles.get(&adresse.idg) {
+            partitions
+                .entry((*key).clone())
+                .or_default()
+                .adresses
+                .push(adresse.clone());
+        }
+    }
+
+    for prix_m2 in &tables.prix_m2 {
+        if let Some(key) = mutations.get(&prix_m2.idm) {
+            partitions
+                .entry(key.clone())
+                .or_default()
+                .prix_m2
+                .push(prix_m2.clone());
+        }
+    }
+
+    partitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_files_are_removed_in_both_layouts() {
+        let folder =
+            std::env::temp_dir().join(format!("data-immo-partition-{}", std::process::id()));
+        let key = PartitionKey {
+            dep: Some("75".to_string()),
+            year: 2023,
+        };
+
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        for (feature_id, paths) in [("1", &mut removed), ("10", &mut kept)] {
+            for table in Tables::NAMES {
+                paths.push(folder.join(OutputLayout::Flat.file_name(table, feature_id)));
+                paths.push(
+                    key.folder(&folder, table)
+                        .join(OutputLayout::Partitioned.file_name(table, feature_id)),
+                );
+            }
+        }
+
+        for path in removed.iter().chain(&kept) {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+
+        remove_feature_files(&folder, "1").unwrap();
+        let exists = |paths: &[PathBuf]| paths.iter().filter(|path| path.exists()).count();
+        let (removed, kept) = (exists(&removed), exists(&kept));
+        let _ = fs::remove_dir_all(&folder);
+
+        assert_eq!(removed, 0);
+        assert_eq!(kept, 2 * Tables::NAMES.len());
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use super::tables::{Classes, Dispositions, Parcelles, PrixM2};
+
+/// Classes counted as built surface, compared in lowercase with the ***libelle*** of the classes.
+const BUILT_CLASSES: [&str; 1] = ["sols"];
+
+/// Rule used to compute the price per square metre of a mutation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceRule {
+    /// The built surface of the mutation (***sbati***) is known : the price is divided by it.
+    BuiltSurface,
+    /// One built class over the sold parcels : the price is divided by its surface.
+    SingleClass,
+    /// Several built classes over the sold parcels : the price is divided by the sum of their surfaces.
+    TotalBuiltSurface,
+    /// The mutation has no built surface, only land : the sale is excluded.
+    LandOnly,
+    /// The sold parcels don't have any class with a surface : the sale is excluded.
+    NoSurface,
+}
+
+impl PriceRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceRule::BuiltSurface => "built_surface",
+            PriceRule::SingleClass => "single_class",
+            PriceRule::TotalBuiltSurface => "total_built_surface",
+            PriceRule::LandOnly => "land_only",
+            PriceRule::NoSurface => "no_surface",
+        }
+    }
+}
+
+impl FromStr for PriceRule {
+    type Err = String;
+
+    fn from_str(regle: &str) -> Result<Self, Self::Err> {
+        match regle {
+            "built_surface" => Ok(PriceRule::BuiltSurface),
+            "single_class" => Ok(PriceRule::SingleClass),
+            "total_built_surface" => Ok(PriceRule::TotalBuiltSurface),
+            "land_only" => Ok(PriceRule::LandOnly),
+            "no_surface" => Ok(PriceRule::NoSurface),
+            regle => Err(format!("Unknown rule {}", regle)),
+        }
+    }
+}
+
+fn is_built(class: &Classes) -> bool {
+    BUILT_CLASSES.contains(&class.libelle.to_lowercase().as_str())
+}
+
+/// Compute the price per square metre of the mutation ***idm***.<br>
+/// The price is the sum of the ***dispositions***, the surface is the built surface ***sbati*** of the mutation :
+/// - known & positive : [PriceRule::BuiltSurface]
+/// - zero : [PriceRule::LandOnly], without price per square metre
+///
+/// When ***sbati*** is missing, the surface is the one of the built ***classes*** of the sold ***parcelles*** only,
+/// (the *'sols'* are the land of the parcel, larger than the premises of e.g. an apartment) :
+/// - one built class : [PriceRule::SingleClass]
+/// - several built classes : [PriceRule::TotalBuiltSurface]
+/// - no built class : [PriceRule::LandOnly] or [PriceRule::NoSurface], without price per square metre
+pub fn compute_prix_m2(
+    idm: u64,
+    sbati: Option<Decimal>,
+    dispositions: &[Dispositions],
+    parcelles: &[Parcelles],
+    classes: &[Classes],
+) -> PrixM2 {
+    let valeur_fonciere = dispositions
+        .iter()
+        .map(|disposition| disposition.valeur_fonciere)
+        .sum::<Decimal>();
+
+    let sold = parcelles
+        .iter()
+        .filter(|parcelle| parcelle.vendu)
+        .map(|parcelle| parcelle.idg)
+        .collect::<HashSet<u64>>();
+
+    let classes = classes
+        .iter()
+        .filter(|class| sold.contains(&class.idg) && class.surface > Decimal::ZERO)
+        .collect::<Vec<&Classes>>();
+
+    let built = classes
+        .iter()
+        .filter(|class| is_built(class))
+        .collect::<Vec<&&Classes>>();
+
+    let (surface, regle) = match sbati {
+        Some(sbati) if sbati > Decimal::ZERO => (Some(sbati), PriceRule::BuiltSurface),
+        Some(_) => (None, PriceRule::LandOnly),
+        None => match built.len() {
+            0 if classes.is_empty() => (None, PriceRule::NoSurface),
+            0 => (None, PriceRule::LandOnly),
+            1 => (Some(built[0].surface), PriceRule::SingleClass),
+            _ => (
+                Some(built.iter().map(|class| class.surface).sum()),
+                PriceRule::TotalBuiltSurface,
+            ),
+        },
+    };
+
+    let prix_m2 = surface
+        .and_then(|surface| valeur_fonciere.checked_div(surface))
+        .map(|prix_m2| prix_m2.round_dp(2));
+
+    PrixM2 {
+        idm,
+        valeur_fonciere,
+        surface,
+        prix_m2,
+        regle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn dispositions(valeurs: &[&str]) -> Vec<Dispositions> {
+        valeurs
+            .iter()
+            .enumerate()
+            .map(|(index, valeur)| Dispositions {
+                idd: index as u64,
+                idm: 1,
+                iddispo: None,
+                nodispo: None,
+                valeur_fonciere: decimal(valeur),
+            })
+            .collect()
+    }
+
+    fn parcelle(idg: u64, vendu: bool) -> Parcelles {
+        Parcelles {
+            idg,
+            idd: 0,
+            idpar: format!("75102000AB{:04}", idg),
+            vendu,
+            nosect: None,
+            noplan: None,
+        }
+    }
+
+    fn class(idg: u64, libelle: &str, surface: &str) -> Classes {
+        Classes {
+            idg,
+            rank: 0,
+            libelle: libelle.to_string(),
+            surface: decimal(surface),
+        }
+    }
+
+    fn assert_prix_m2(
+        prix_m2: &PrixM2,
+        regle: PriceRule,
+        surface: Option<&str>,
+        prix: Option<&str>,
+    ) {
+        assert_eq!(prix_m2.regle, regle);
+        assert_eq!(prix_m2.surface, surface.map(decimal));
+        assert_eq!(prix_m2.prix_m2, prix.map(decimal));
+    }
+
+    #[test]
+    fn built_surface() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            Some(decimal("54.20")),
+            &dispositions(&["500000", "12000"]),
+            &[parcelle(1, true)],
+            &[class(1, "Sols", "300")],
+        );
+
+        assert_eq!(prix_m2.valeur_fonciere, decimal("512000"));
+        assert_prix_m2(
+            &prix_m2,
+            PriceRule::BuiltSurface,
+            Some("54.20"),
+            Some("9446.49"),
+        );
+    }
+
+    #[test]
+    fn single_class() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["300000"]),
+            &[parcelle(1, true)],
+            &[class(1, "Sols", "120"), class(1, "Jardins", "500")],
+        );
+
+        assert_prix_m2(&prix_m2, PriceRule::SingleClass, Some("120"), Some("2500"));
+    }
+
+    #[test]
+    fn several_classes() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["300000"]),
+            &[parcelle(1, true), parcelle(2, true)],
+            &[class(1, "Sols", "100"), class(2, "SOLS", "50")],
+        );
+
+        assert_prix_m2(
+            &prix_m2,
+            PriceRule::TotalBuiltSurface,
+            Some("150"),
+            Some("2000"),
+        );
+    }
+
+    #[test]
+    fn land_only() {
+        let land = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["20000"]),
+            &[parcelle(1, true)],
+            &[class(1, "Terres", "10000")],
+        );
+        assert_prix_m2(&land, PriceRule::LandOnly, None, None);
+
+        // Without built surface the sols of the parcel are ignored
+        let no_building = compute_prix_m2(
+            1,
+            Some(Decimal::ZERO),
+            &dispositions(&["20000"]),
+            &[parcelle(1, true)],
+            &[class(1, "Sols", "800")],
+        );
+        assert_prix_m2(&no_building, PriceRule::LandOnly, None, None);
+    }
+
+    #[test]
+    fn no_surface() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["20000"]),
+            &[parcelle(1, true)],
+            &[],
+        );
+
+        assert_prix_m2(&prix_m2, PriceRule::NoSurface, None, None);
+    }
+
+    #[test]
+    fn zero_surface() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["20000"]),
+            &[parcelle(1, true)],
+            &[class(1, "Sols", "0")],
+        );
+
+        assert_prix_m2(&prix_m2, PriceRule::NoSurface, None, None);
+    }
+
+    #[test]
+    fn unsold_parcel() {
+        let prix_m2 = compute_prix_m2(
+            1,
+            None,
+            &dispositions(&["300000"]),
+            &[parcelle(1, true), parcelle(2, false)],
+            &[class(1, "Sols", "100"), class(2, "Sols", "900")],
+        );
+
+        assert_prix_m2(&prix_m2, PriceRule::SingleClass, Some("100"), Some("3000"));
+    }
+
+    #[test]
+    fn parsed_rules() {
+        for regle in [
+            PriceRule::BuiltSurface,
+            PriceRule::SingleClass,
+            PriceRule::TotalBuiltSurface,
+            PriceRule::LandOnly,
+            PriceRule::NoSurface,
+        ] {
+            assert_eq!(regle.as_str().parse::<PriceRule>(), Ok(regle));
+        }
+        assert!("main_class".parse::<PriceRule>().is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde_json::Value;
+
+pub const QUARANTINE_FOLDER: &str = "data/DVF/quarantine";
+
+/// Record of the API response skipped because it can't be transformed.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantineRecord {
+    pub path: String,
+    pub reason: String,
+    pub record: Value,
+}
+
+impl QuarantineRecord {
+    pub fn new(path: String, reason: String, record: Value) -> Self {
+        Self {
+            path,
+            reason,
+            record,
+        }
+    }
+}
+
+/// Write the ***records*** in the JSONL file ***path***, one record per line.<br>
+/// The file is overwritten, as the Parquet files of the feature, so a re-run doesn't duplicate the records.
+pub fn write_to_jsonl(records: &[QuarantineRecord], path: &PathBuf) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open the file {:?} : {}", path, e))?;
+
+    for record in records {
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize the quarantine record : {}", e))?;
+        line.push('\n');
+
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write the file {:?} : {}", path, e))?;
+    }
+
+    Ok(())
+}
@@ -1,27 +1,70 @@
-use mylog::error;
+use chrono::NaiveDate;
+use parquet_data_derive::ParquetData;
+use rust_decimal::Decimal;
+use serde::Serialize;
 use serde_json::{self, Map, Value};
 
-/// Represent the SQL table '***Mutation***'
-#[derive(Debug, Clone)]
+use super::address::{code_departement, format_adresse, normalize_adresse};
+use super::api_model::{ApiAdresse, Dcnt, Disposition, Feature, Parcelle, RecordError};
+use super::categories::{NatureMutation, Typologie};
+use super::parquet_data::{SURFACE_PRECISION, VALEUR_FONCIERE_PRECISION, check_precision};
+use super::parquet_sink::{
+    ADRESSES_WRITER_CONFIG, MUTATIONS_WRITER_CONFIG, PARCELLES_WRITER_CONFIG,
+};
+use super::price::PriceRule;
+use super::utils::{parse_date, stable_id};
+
+/// Represent the SQL table '***Mutations***' : one row per mutation of the API.
+#[derive(Debug, Clone, ParquetData)]
+#[parquet(writer = MUTATIONS_WRITER_CONFIG)]
 pub struct Mutation {
-    pub idg: u64,
-    pub idpar: String,
+    pub idm: u64,
     pub idmutation: u64,
-    pub shared_props: SharedMutationProps,
-    pub adresse: Adresse,
-    pub valeur_fonciere: f64,
-    pub vendu: bool,
+    pub vefa: bool,
+    #[parquet(derived(name = "code_typologie", with = Typologie::code))]
+    pub typologie: Typologie,
+    pub datemut: NaiveDate,
+    #[parquet(derived(name = "code_nature", with = NatureMutation::code))]
+    pub nature: NatureMutation,
+    pub codtypbien: Option<String>,
+    pub libtypbien: Option<String>,
+    pub sbati: Option<Decimal>,
+    pub sterr: Option<Decimal>,
+    pub nblocmut: Option<u32>,
+    pub nbpar: Option<u32>,
+    pub coddep: Option<String>,
+    pub l_codinsee: Option<Vec<String>>,
+    pub longitude: Option<f64>,
+    pub latitude: Option<f64>,
+    pub geometry: Option<Vec<u8>>,
+    #[parquet(flatten(prefix = "source_"))]
+    pub source: FeatureSource,
 }
 
-#[derive(Debug, Clone)]
-pub struct SharedMutationProps {
-    pub vefa: bool,
-    pub typologie: String,
-    pub datemut: String,
-    pub nature: String,
+/// Represent the SQL table '***Dispositions***' : the price is stored once per disposition.
+#[derive(Debug, Clone, ParquetData)]
+pub struct Dispositions {
+    pub idd: u64,
+    pub idm: u64,
+    pub iddispo: Option<u64>,
+    pub nodispo: Option<u64>,
+    #[parquet(precision = VALEUR_FONCIERE_PRECISION)]
+    pub valeur_fonciere: Decimal,
 }
 
-#[derive(Debug, Clone)]
+/// Represent the SQL table '***Parcelles***' : the parcels of a disposition.
+#[derive(Debug, Clone, ParquetData)]
+#[parquet(writer = PARCELLES_WRITER_CONFIG)]
+pub struct Parcelles {
+    pub idg: u64,
+    pub idd: u64,
+    pub idpar: String,
+    pub vendu: bool,
+    pub nosect: Option<String>,
+    pub noplan: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, ParquetData)]
 pub struct Adresse {
     pub btq: Option<String>,
     pub voie: Option<String>,
@@ -32,122 +75,379 @@ pub struct Adresse {
     pub codepostal: Option<String>,
 }
 
-/// Represent the SQL table '***Classes***'
-#[derive(Debug, Clone)]
+/// Properties of the GeoJSON feature (commune) used to query the API.
+#[derive(Debug, Clone, Default, ParquetData)]
+pub struct FeatureSource {
+    pub code_insee: Option<String>,
+    pub nom_commune: Option<String>,
+    pub code_dep: Option<String>,
+}
+
+/// Represent the SQL table '***Adresses***' : every address of a parcel, ordered by ***rank***.
+#[derive(Debug, Clone, ParquetData)]
+#[parquet(writer = ADRESSES_WRITER_CONFIG)]
+pub struct Adresses {
+    pub idg: u64,
+    pub rank: u32,
+    #[parquet(flatten)]
+    pub adresse: Adresse,
+    pub adresse_complete: Option<String>,
+    pub normalisee: bool,
+}
+
+/// Represent the SQL table '***Classes***' : the premises of a parcel,
+/// ***rank*** is the index of the entry in the *'dcnt'* of the parcel.
+#[derive(Debug, Clone, ParquetData)]
 pub struct Classes {
     pub idg: u64,
+    pub rank: u32,
     pub libelle: String,
-    pub surface: f64,
+    pub surface: Decimal,
+}
+
+/// Rules applied by [Classes::extract] to drop the *'dcnt'* entries of a parcel.
+#[derive(Debug, Clone, Copy)]
+pub struct ClassFilter {
+    /// Entries with a surface strictly below are dropped.
+    pub min_surface: Decimal,
+    /// Drop the entries without ***libregroupement***, else they're kept with [UNKNOWN_LIBELLE].
+    pub drop_missing_libelle: bool,
+    /// Put the dropped entries in the quarantine.
+    pub quarantine: bool,
+}
+
+pub const UNKNOWN_LIBELLE: &str = "INCONNU";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassRejection {
+    MissingSurface,
+    SurfaceBelowThreshold,
+    MissingLibelle,
+}
+
+/// Entry of *'dcnt'* dropped by the [ClassFilter], with the path of the entry in the API response.<br>
+/// ***idg*** & ***rank*** are the key the class would have had in [Classes].
+#[derive(Debug, Clone)]
+pub struct RejectedClass {
+    pub idg: u64,
+    pub rank: u32,
+    pub path: String,
+    pub reason: ClassRejection,
+    pub record: Value,
+}
+
+/// Represent the SQL table '***Prix_M2***' : the price per square metre of a mutation,
+/// with the [PriceRule] applied to compute it.
+#[derive(Debug, Clone, ParquetData)]
+pub struct PrixM2 {
+    pub idm: u64,
+    #[parquet(precision = VALEUR_FONCIERE_PRECISION)]
+    pub valeur_fonciere: Decimal,
+    pub surface: Option<Decimal>,
+    #[parquet(precision = VALEUR_FONCIERE_PRECISION)]
+    pub prix_m2: Option<Decimal>,
+    pub regle: PriceRule,
+}
+
+/// Rows of every table extracted from the API responses.
+#[derive(Debug, Clone, Default)]
+pub struct Tables {
+    pub mutations: Vec<Mutation>,
+    pub dispositions: Vec<Dispositions>,
+    pub parcelles: Vec<Parcelles>,
+    pub classes: Vec<Classes>,
+    pub adresses: Vec<Adresses>,
+    pub prix_m2: Vec<PrixM2>,
+}
+
+impl Tables {
+    /// Names of the tables, in the order of [Tables::sizes].
+    pub const NAMES: [&str; 6] = [
+        "mutations",
+        "dispositions",
+        "parcelles",
+        "classes",
+        "adresses",
+        "prix_m2",
+    ];
+
+    /// Return the number of rows of each table, to [Tables::truncate] them back after an error.
+    pub fn sizes(&self) -> [usize; 6] {
+        [
+            self.mutations.len(),
+            self.dispositions.len(),
+            self.parcelles.len(),
+            self.classes.len(),
+            self.adresses.len(),
+            self.prix_m2.len(),
+        ]
+    }
+
+    pub fn truncate(&mut self, sizes: [usize; 6]) {
+        self.mutations.truncate(sizes[0]);
+        self.dispositions.truncate(sizes[1]);
+        self.parcelles.truncate(sizes[2]);
+        self.classes.truncate(sizes[3]);
+        self.adresses.truncate(sizes[4]);
+        self.prix_m2.truncate(sizes[5]);
+    }
+
+    /// Move the rows of the ***other*** tables at the end of these ones.
+    pub fn append(&mut self, other: &mut Tables) {
+        self.mutations.append(&mut other.mutations);
+        self.dispositions.append(&mut other.dispositions);
+        self.parcelles.append(&mut other.parcelles);
+        self.classes.append(&mut other.classes);
+        self.adresses.append(&mut other.adresses);
+        self.prix_m2.append(&mut other.prix_m2);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+}
+
+/// Return an Error on the ***path*** of the record if the ***value*** doesn't fit in its column.
+fn check_decimal(value: Option<&Decimal>, precision: u8, path: &str) -> Result<(), RecordError> {
+    match value {
+        Some(value) => check_precision(value, precision).map_err(|e| RecordError::new(path, &e)),
+        None => Ok(()),
+    }
 }
 
 impl Mutation {
+    /// Extract the properties of the mutation ***feature***.<br>
+    /// The geometry is kept as WKB only if ***with_geometry***, its centroid is always kept.<br>
+    /// The ***idm*** is derived from the ***idmutation***, to keep the same key across runs.<br>
+    /// Return an Error if the ***datemut*** is invalid or a surface doesn't fit in its column,
+    /// to put the record in the quarantine.
     pub fn extract(
-        map: Map<String, Value>,
-        shared_props: &SharedMutationProps,
-        valeurfonc: f64,
-        idmutation: u64,
-        id: u64,
-    ) -> Result<Mutation, ()> {
-        let idpar = String::from(
-            map.get("idpar")
-                .ok_or(())
-                .map_err(|_| error!("Failed to get the value of the key 'idpar'"))?
-                .as_str()
-                .ok_or(())
-                .map_err(|_| error!("Inconsistant value : Expected a Str"))?,
-        );
-
-        let vendu = map
-            .get("parcvendue")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'parcvendue'"))?
-            .as_bool()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Boolean"))?;
-
-        let adresses = map
-            .get("adresses")
-            .ok_or(())
-            .map_err(|_| error!("Failed to get the value of the key 'adresses'"))?;
-        let adresse = Adresse::extract(adresses)?;
+        feature: &Feature,
+        source: &FeatureSource,
+        with_geometry: bool,
+    ) -> Result<Mutation, RecordError> {
+        let properties = &feature.properties;
+        let centroid = feature.geometry.as_ref().and_then(|g| g.centroid());
+
+        let idmutation = properties
+            .idmutation
+            .or_else(|| properties.dispositions.first().map(|d| d.idmutation))
+            .ok_or_else(|| RecordError::new("idmutation", "missing field `idmutation`"))?;
+
+        let datemut =
+            parse_date(&properties.datemut).map_err(|e| RecordError::new("datemut", &e))?;
+
+        check_decimal(properties.sbati.as_ref(), SURFACE_PRECISION, "sbati")?;
+        check_decimal(properties.sterr.as_ref(), SURFACE_PRECISION, "sterr")?;
 
         Ok(Mutation {
-            idg: id,
-            idpar,
+            idm: stable_id("mutations", &[&idmutation.to_string()]),
             idmutation,
-            shared_props: shared_props.clone(),
-            adresse,
-            valeur_fonciere: valeurfonc,
-            vendu,
+            vefa: properties.vefa,
+            typologie: Typologie::from_libelle(&properties.typologie.libelle),
+            datemut,
+            nature: NatureMutation::from_libelle(&properties.nature_mutation.libelle),
+            codtypbien: properties.codtypbien.clone(),
+            libtypbien: properties.libtypbien.clone(),
+            sbati: properties.sbati,
+            sterr: properties.sterr,
+            nblocmut: properties.nblocmut,
+            nbpar: properties.nbpar,
+            coddep: properties.coddep.clone(),
+            l_codinsee: properties.l_codinsee.clone(),
+            longitude: centroid.map(|point| point.x),
+            latitude: centroid.map(|point| point.y),
+            geometry: feature
+                .geometry
+                .as_ref()
+                .filter(|_| with_geometry)
+                .map(|g| g.to_wkb()),
+            source: source.clone(),
         })
     }
 }
 
-impl SharedMutationProps {
-    pub fn new(vefa: bool, typologie: String, datemut: String, nature: String) -> Self {
-        Self {
-            vefa,
-            typologie,
-            datemut,
-            nature,
+impl ClassRejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClassRejection::MissingSurface => "missing_surface",
+            ClassRejection::SurfaceBelowThreshold => "surface_below_threshold",
+            ClassRejection::MissingLibelle => "missing_libelle",
         }
     }
 }
 
-impl Adresse {
-    fn extract(value: &Value) -> Result<Adresse, ()> {
-        let adresses = value
-            .as_array()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected an Array<Value>"))?;
-        let adresse = adresses
-            .first()
-            .ok_or(())
-            .map_err(|_| error!("Empty adresses"))?
-            .as_object()
-            .ok_or(())
-            .map_err(|_| error!("Inconsistant value : Expected a Map<String, Value>"))?;
-
-        Ok(Adresse {
-            btq: unwrap_value(adresse.get("btq")),
-            voie: unwrap_value(adresse.get("voie")),
-            novoie: unwrap_value(adresse.get("novoie")),
-            codvoie: unwrap_value(adresse.get("codvoie")),
-            commune: unwrap_value(adresse.get("commune")),
-            typvoie: unwrap_value(adresse.get("typvoie")),
-            codepostal: unwrap_value(adresse.get("codepostal")),
+impl Dispositions {
+    /// The ***idd*** is derived from the mutation and the ***iddispo***,
+    /// or the ***index*** of the disposition in the mutation when it's missing.<br>
+    /// Return an Error if the ***valeurfonc*** doesn't fit in its column.
+    pub fn extract(
+        disposition: &Disposition,
+        idm: u64,
+        index: usize,
+    ) -> Result<Dispositions, RecordError> {
+        check_decimal(
+            Some(&disposition.valeurfonc),
+            VALEUR_FONCIERE_PRECISION,
+            "valeurfonc",
+        )?;
+
+        let key = match disposition.iddispo {
+            Some(iddispo) => iddispo.to_string(),
+            None => format!("#{}", index),
+        };
+
+        Ok(Dispositions {
+            idd: stable_id("dispositions", &[&idm.to_string(), &key]),
+            idm,
+            iddispo: disposition.iddispo,
+            nodispo: disposition.nodispo,
+            valeur_fonciere: disposition.valeurfonc,
         })
     }
 }
 
-impl Classes {
-    pub fn extract(values: &[Value], id: u64) -> Result<Vec<Classes>, ()> {
-        let values = values
+impl Parcelles {
+    /// The ***idg*** is derived from the disposition and the ***idpar*** of the parcel.
+    pub fn extract(parcelle: &Parcelle, idd: u64) -> Parcelles {
+        Parcelles {
+            idg: stable_id("parcelles", &[&idd.to_string(), &parcelle.idpar]),
+            idd,
+            idpar: parcelle.idpar.clone(),
+            vendu: parcelle.parcvendue,
+            nosect: parcelle.nosect.clone(),
+            noplan: parcelle.noplan.clone(),
+        }
+    }
+}
+
+impl FeatureSource {
+    /// Build the source from the properties of a *'France GeoJSON'* commune feature.<br>
+    /// The department code is deduced from the INSEE code (3 characters for the overseas departments).
+    pub fn from_properties(properties: Option<&Map<String, Value>>) -> Self {
+        let code_insee = properties.and_then(|map| unwrap_value(map.get("code")));
+        let nom_commune = properties.and_then(|map| unwrap_value(map.get("nom")));
+
+        let code_dep = code_insee.as_deref().and_then(code_departement);
+
+        Self {
+            code_insee,
+            nom_commune,
+            code_dep,
+        }
+    }
+}
+
+impl From<&ApiAdresse> for Adresse {
+    fn from(adresse: &ApiAdresse) -> Self {
+        Adresse {
+            btq: adresse.btq.clone(),
+            voie: adresse.voie.clone(),
+            novoie: adresse.novoie.clone(),
+            codvoie: adresse.codvoie.clone(),
+            commune: adresse.commune.clone(),
+            typvoie: adresse.typvoie.clone(),
+            codepostal: adresse.codepostal.clone(),
+        }
+    }
+}
+
+impl Adresses {
+    /// Keep every address of the parcel ***idpar***, normalized, in the order returned by the API.
+    pub fn extract(adresses: &[ApiAdresse], idpar: &str, idg: u64) -> Vec<Adresses> {
+        let code_dep = code_departement(idpar);
+
+        adresses
             .iter()
-            .flat_map(|v| {
-                if let Value::Object(map) = v {
-                    match (map.get("surface"), map.get("libregroupement")) {
-                        (Some(surface), Some(name)) => {
-                            let surface = surface.as_f64().ok_or(())?;
-                            if surface < 1f64 {
-                                return Err(());
-                            }
-                            let name = String::from(name.as_str().ok_or(())?);
-                            Ok(Classes {
-                                idg: id,
-                                libelle: name,
-                                surface,
-                            })
-                        }
-                        _ => Err(()),
-                    }
-                } else {
-                    Err(())
+            .enumerate()
+            .map(|(rank, adresse)| {
+                let mut adresse = Adresse::from(adresse);
+                let normalisee = normalize_adresse(&mut adresse, code_dep.as_deref());
+
+                Adresses {
+                    idg,
+                    rank: rank as u32,
+                    adresse_complete: format_adresse(&adresse),
+                    adresse,
+                    normalisee,
                 }
             })
-            .collect::<Vec<Classes>>();
+            .collect()
+    }
+}
+
+impl Classes {
+    /// Keep the *'dcnt'* entries of the parcel that pass the ***filter***, the others are pushed into ***rejected***.<br>
+    /// Return an Error if the surface of a kept entry doesn't fit in its column.
+    pub fn extract(
+        values: &[Dcnt],
+        idg: u64,
+        filter: &ClassFilter,
+        rejected: &mut Vec<RejectedClass>,
+    ) -> Result<Vec<Classes>, RecordError> {
+        let mut classes: Vec<Classes> = Vec::new();
+
+        for (index, dcnt) in values.iter().enumerate() {
+            let libelle = dcnt
+                .libregroupement
+                .as_deref()
+                .map(str::trim)
+                .filter(|libelle| !libelle.is_empty());
+
+            let result = match (dcnt.surface, libelle) {
+                (None, _) => Err(ClassRejection::MissingSurface),
+                (Some(surface), _) if surface < filter.min_surface => {
+                    Err(ClassRejection::SurfaceBelowThreshold)
+                }
+                (Some(_), None) if filter.drop_missing_libelle => {
+                    Err(ClassRejection::MissingLibelle)
+                }
+                (Some(surface), libelle) => Ok(Classes {
+                    idg,
+                    rank: index as u32,
+                    libelle: libelle.unwrap_or(UNKNOWN_LIBELLE).to_string(),
+                    surface,
+                }),
+            };
+
+            match result {
+                Ok(class) => {
+                    check_decimal(
+                        Some(&class.surface),
+                        SURFACE_PRECISION,
+                        &format!("dcnt[{}].surface", index),
+                    )?;
+                    classes.push(class)
+                }
+                Err(reason) => rejected.push(RejectedClass {
+                    idg,
+                    rank: index as u32,
+                    path: format!("dcnt[{}]", index),
+                    reason,
+                    record: dcnt.raw.clone(),
+                }),
+            }
+        }
+        Ok(classes)
+    }
+}
 
-        Ok(values)
+impl PrixM2 {
+    /// Return an Error if a value computed from the record doesn't fit in its column,
+    /// e.g. the sum of the prices of the dispositions.
+    pub fn check(&self) -> Result<(), RecordError> {
+        check_decimal(
+            Some(&self.valeur_fonciere),
+            VALEUR_FONCIERE_PRECISION,
+            "prix_m2.valeur_fonciere",
+        )?;
+        check_decimal(self.surface.as_ref(), SURFACE_PRECISION, "prix_m2.surface")?;
+        check_decimal(
+            self.prix_m2.as_ref(),
+            VALEUR_FONCIERE_PRECISION,
+            "prix_m2.prix_m2",
+        )
     }
 }
 
@@ -1,10 +1,165 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta};
 
-pub fn parse_date(date_str: &str) -> Option<i32> {
-    NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+/// First day covered by the DVF, the mutations before are rejected (and put in the quarantine with the reason).<br>
+/// The DVF open data of the DGFiP starts on 2014-01-01 (its first release covered 2014 to 2018)
+/// and the DVF+ open data of the Cerema is built from it, so an earlier date is an error of the record.<br>
+/// Move it back to query a source with an older history, e.g. the DV3F which starts in 2010.
+const DVF_START: NaiveDate = match NaiveDate::from_ymd_opt(2014, 1, 1) {
+    Some(date) => date,
+    None => panic!("Invalid DVF start date"),
+};
+
+const UNIX_EPOCH: NaiveDate = match NaiveDate::from_ymd_opt(1970, 1, 1) {
+    Some(date) => date,
+    None => panic!("Invalid UNIX epoch"),
+};
+
+const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Parse an ISO date or datetime (e.g. *'2023-05-17'*, *'2023-05-17T00:00:00'* or *'2023-05-17T00:00:00+02:00'*)
+/// and check it's in the period covered by the DVF.
+pub fn parse_date(date_str: &str) -> Result<NaiveDate, String> {
+    let date_str = date_str.trim();
+
+    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
         .ok()
-        .map(|date| {
-            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
-            date.signed_duration_since(epoch).num_days() as i32
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(date_str)
+                .ok()
+                .map(|datetime| datetime.date_naive())
         })
+        .or_else(|| {
+            DATETIME_FORMATS.iter().find_map(|format| {
+                NaiveDateTime::parse_from_str(date_str, format)
+                    .ok()
+                    .map(|datetime| datetime.date())
+            })
+        })
+        .ok_or_else(|| format!("invalid date '{}'", date_str))?;
+
+    if date < DVF_START {
+        Err(format!(
+            "date '{}' is before the DVF coverage ({})",
+            date, DVF_START
+        ))
+    } else if date > Local::now().date_naive() {
+        Err(format!("date '{}' is in the future", date))
+    } else {
+        Ok(date)
+    }
+}
+
+/// Return the number of days since the UNIX epoch, as stored in an Arrow ***Date32***.
+pub fn days_since_epoch(date: &NaiveDate) -> i32 {
+    date.signed_duration_since(UNIX_EPOCH).num_days() as i32
+}
+
+/// Return the date of an Arrow ***Date32***, from its number of ***days*** since the UNIX epoch.
+pub fn date_from_days(days: i32) -> Option<NaiveDate> {
+    UNIX_EPOCH.checked_add_signed(TimeDelta::days(days as i64))
+}
+
+/// Offset basis & prime of the 64 bits FNV-1a hash, fixed to get the same ids on every run.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Return the deterministic id of a row of the ***table***, from its business ***key***.<br>
+/// The parts of the key are separated to not mix *'1'* + *'23'* with *'12'* + *'3'*,
+/// and the id is kept below ***i64::MAX*** to fit in the *BIGINT* columns.
+pub fn stable_id(table: &str, key: &[&str]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+
+    for part in std::iter::once(&table).chain(key) {
+        for byte in part.bytes().chain(std::iter::once(0x1F)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    hash & i64::MAX as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn accepted_date_formats() {
+        for value in [
+            "2023-05-17",
+            " 2023-05-17 ",
+            "2023-05-17T00:00:00+02:00",
+            "2023-05-17T23:30:00Z",
+            "2023-05-17T08:15:00",
+            "2023-05-17T08:15:00.250",
+            "2023-05-17 08:15:00",
+            "2023-05-17T08:15",
+            "2023-05-17 08:15",
+        ] {
+            assert_eq!(parse_date(value), Ok(date(2023, 5, 17)), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejected_dates() {
+        for value in ["", "17/05/2023", "2023-13-01", "2023-05-17T25:00"] {
+            assert!(parse_date(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn dates_in_the_dvf_coverage() {
+        assert_eq!(parse_date("2014-01-01"), Ok(DVF_START));
+        assert!(parse_date("2013-12-31").is_err());
+        assert!(parse_date("2013-12-31T23:59:59").is_err());
+
+        let today = Local::now().date_naive();
+        let tomorrow = today + TimeDelta::days(1);
+        assert_eq!(parse_date(&today.to_string()), Ok(today));
+        assert!(parse_date(&tomorrow.to_string()).is_err());
+    }
+
+    #[test]
+    fn date32_round_trip() {
+        assert_eq!(days_since_epoch(&UNIX_EPOCH), 0);
+        assert_eq!(days_since_epoch(&date(2023, 5, 17)), 19494);
+        assert_eq!(date_from_days(19494), Some(date(2023, 5, 17)));
+        assert_eq!(date_from_days(-1), Some(date(1969, 12, 31)));
+    }
+
+    #[test]
+    fn stable_ids() {
+        // Fixed values : a change of the hash changes every id already loaded in the warehouse
+        assert_eq!(stable_id("mutations", &["1"]), 8530667176456639168);
+        assert_eq!(stable_id("dispositions", &["1"]), 3151260187286091734);
+        assert_eq!(
+            stable_id("parcelles", &["42", "75102000AB0001"]),
+            4703643774404360452
+        );
+        assert_eq!(stable_id("mutations", &["12", "3"]), 6644874162743676010);
+        assert_eq!(stable_id("mutations", &["1", "23"]), 8079914451461723168);
+        assert_eq!(stable_id("", &[]), 3414804168584584078);
+    }
+
+    #[test]
+    fn stable_ids_without_collision() {
+        let mut ids = std::collections::HashSet::new();
+
+        for table in ["mutations", "dispositions", "parcelles"] {
+            for key in 0..10_000 {
+                let id = stable_id(table, &[&key.to_string()]);
+                assert!(id <= i64::MAX as u64);
+                assert!(ids.insert(id), "collision on {} {}", table, key);
+            }
+        }
+    }
 }